@@ -0,0 +1,196 @@
+use serde::Serialize;
+
+use crate::{
+    error::ReleasyCoreError,
+    event::{github_api_url, post_to_github},
+    repo::Repo,
+};
+
+/// State of a commit status, as shown next to the commit on GitHub.
+#[derive(Serialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum CommitState {
+    Pending,
+    Success,
+    Failure,
+    Error,
+}
+
+/// A commit status reporting the CI of a downstream repo on an upstream commit.
+#[derive(Serialize, Clone, Debug, PartialEq, Eq)]
+pub struct CommitStatus {
+    state: CommitState,
+    context: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    description: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    target_url: Option<String>,
+}
+
+impl CommitStatus {
+    /// Creates a status of the CI of the given downstream repo, with the `releasy/<name>` context.
+    pub fn new(state: CommitState, downstream_repo: &Repo) -> Self {
+        Self {
+            state,
+            context: format!("releasy/{}", downstream_repo.name()),
+            description: None,
+            target_url: None,
+        }
+    }
+
+    /// Sets the short description shown next to the status.
+    pub fn with_description(mut self, description: Option<String>) -> Self {
+        self.description = description;
+        self
+    }
+
+    /// Sets the URL the status links to, e.g. the downstream CI run.
+    pub fn with_target_url(mut self, target_url: Option<String>) -> Self {
+        self.target_url = target_url;
+        self
+    }
+
+    pub fn state(&self) -> CommitState {
+        self.state
+    }
+
+    pub fn context(&self) -> &str {
+        &self.context
+    }
+
+    /// Returns the URL of the endpoint of the GitHub API at `api_url` creating statuses of the
+    /// given commit.
+    fn request_url(api_url: &str, repo: &Repo, commit_hash: &str) -> String {
+        format!(
+            "{api_url}/repos/{}/{}/statuses/{commit_hash}",
+            repo.owner(),
+            repo.name()
+        )
+    }
+
+    /// Posts this status on a commit of the given repo.
+    pub async fn post(
+        &self,
+        repo: &Repo,
+        commit_hash: &str,
+        github_token: &str,
+    ) -> Result<(), ReleasyCoreError> {
+        self.post_to(&github_api_url(), repo, commit_hash, github_token)
+            .await
+    }
+
+    /// Posts this status on a commit of the given repo through the GitHub API at `api_url`.
+    pub async fn post_to(
+        &self,
+        api_url: &str,
+        repo: &Repo,
+        commit_hash: &str,
+        github_token: &str,
+    ) -> Result<(), ReleasyCoreError> {
+        let body =
+            serde_json::to_string(self).map_err(ReleasyCoreError::FailedToSerializeEventToJSON)?;
+        post_to_github(
+            Self::request_url(api_url, repo, commit_hash),
+            body,
+            github_token,
+            repo,
+        )
+        .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{CommitState, CommitStatus};
+    use crate::repo::Repo;
+    use std::{
+        io::{BufRead, BufReader, Read, Write},
+        net::TcpListener,
+        thread::JoinHandle,
+    };
+
+    #[test]
+    fn commit_status_request() {
+        let fuel_core = Repo::new("fuel-core".to_string(), "FuelLabs".to_string());
+        let fuels_rs = Repo::new("fuels-rs".to_string(), "FuelLabs".to_string());
+        let status = CommitStatus::new(CommitState::Failure, &fuels_rs).with_target_url(Some(
+            "https://github.com/FuelLabs/fuels-rs/actions/runs/42".to_string(),
+        ));
+
+        assert_eq!(
+            CommitStatus::request_url("https://api.github.com", &fuel_core, "9e3b1c7a"),
+            "https://api.github.com/repos/FuelLabs/fuel-core/statuses/9e3b1c7a"
+        );
+        assert_eq!(
+            serde_json::to_string(&status).unwrap(),
+            r#"{"state":"failure","context":"releasy/fuels-rs","target_url":"https://github.com/FuelLabs/fuels-rs/actions/runs/42"}"#
+        );
+    }
+
+    /// Accepts a single request and answers it with `201 Created`, returning the request line
+    /// and the body it received.
+    fn mock_github_api() -> (String, JoinHandle<(String, String)>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let api_url = format!("http://{}", listener.local_addr().unwrap());
+        let server = std::thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            let mut reader = BufReader::new(stream);
+            let mut request_line = String::new();
+            reader.read_line(&mut request_line).unwrap();
+            let mut content_length = 0;
+            loop {
+                let mut header = String::new();
+                reader.read_line(&mut header).unwrap();
+                if header.trim().is_empty() {
+                    break;
+                }
+                if let Some((name, value)) = header.split_once(':') {
+                    if name.eq_ignore_ascii_case("content-length") {
+                        content_length = value.trim().parse().unwrap();
+                    }
+                }
+            }
+            let mut body = vec![0; content_length];
+            reader.read_exact(&mut body).unwrap();
+            reader
+                .get_mut()
+                .write_all(b"HTTP/1.1 201 Created\r\ncontent-length: 2\r\n\r\n{}")
+                .unwrap();
+            (
+                request_line.trim().to_string(),
+                String::from_utf8(body).unwrap(),
+            )
+        });
+        (api_url, server)
+    }
+
+    #[test]
+    fn post_commit_status_to_github_api() {
+        let fuel_core = Repo::new("fuel-core".to_string(), "FuelLabs".to_string());
+        let fuels_rs = Repo::new("fuels-rs".to_string(), "FuelLabs".to_string());
+        let status = CommitStatus::new(CommitState::Pending, &fuels_rs).with_target_url(Some(
+            "https://github.com/FuelLabs/fuels-rs/actions/runs/42".to_string(),
+        ));
+        let (api_url, server) = mock_github_api();
+
+        tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .unwrap()
+            .block_on(status.post_to(&api_url, &fuel_core, "9e3b1c7a", "token"))
+            .unwrap();
+
+        let (request_line, body) = server.join().unwrap();
+        assert_eq!(
+            request_line,
+            "POST /repos/FuelLabs/fuel-core/statuses/9e3b1c7a HTTP/1.1"
+        );
+        let body: serde_json::Value = serde_json::from_str(&body).unwrap();
+        assert_eq!(body["context"], "releasy/fuels-rs");
+        assert_eq!(body["state"], "pending");
+        assert_eq!(
+            body["target_url"],
+            "https://github.com/FuelLabs/fuels-rs/actions/runs/42"
+        );
+    }
+}
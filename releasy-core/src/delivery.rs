@@ -0,0 +1,243 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+use crate::{
+    error::ReleasyCoreError,
+    event::{github_api_url, post_to_github, send, ClientPayload, Event},
+    repo::Repo,
+};
+
+/// How events are delivered to a repo.
+///
+/// By default events are sent as `repository_dispatch` events, which are only received by the
+/// workflows of the default branch. With `workflow-dispatch`, a named workflow is triggered on a
+/// given ref instead:
+///
+/// ```toml
+/// [repo.sway.settings.delivery]
+/// mode = "workflow-dispatch"
+/// workflow = "releasy.yml"
+/// ref = "master"
+/// ```
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, Eq)]
+#[serde(tag = "mode", rename_all = "kebab-case")]
+pub enum Delivery {
+    #[default]
+    RepositoryDispatch,
+    WorkflowDispatch {
+        /// File name or id of the workflow.
+        workflow: String,
+        /// Branch or tag the workflow is run on. If omitted, the default branch declared in the
+        /// settings of the repo, or the one reported by the GitHub API.
+        #[serde(rename = "ref", default, skip_serializing_if = "Option::is_none")]
+        git_ref: Option<String>,
+    },
+}
+
+impl Delivery {
+    /// Returns the URL of the GitHub API endpoint delivering events to the target repo.
+    pub(crate) fn request_url(&self, target_repo: &Repo) -> String {
+        let repo_url = repo_url(target_repo);
+        match self {
+            Delivery::RepositoryDispatch => format!("{repo_url}/dispatches"),
+            Delivery::WorkflowDispatch { workflow, .. } => {
+                format!("{repo_url}/actions/workflows/{workflow}/dispatches")
+            }
+        }
+    }
+
+    /// Returns the request body delivering the event.
+    pub(crate) fn request_body(&self, event: &Event) -> Result<String, ReleasyCoreError> {
+        match self {
+            Delivery::RepositoryDispatch => serde_json::to_string(event),
+            Delivery::WorkflowDispatch { workflow, git_ref } => {
+                let request = WorkflowDispatchRequest {
+                    git_ref: git_ref
+                        .clone()
+                        .ok_or_else(|| ReleasyCoreError::MissingWorkflowRef(workflow.clone()))?,
+                    inputs: WorkflowInputs::try_from(event)?.into_map(),
+                };
+                serde_json::to_string(&request)
+            }
+        }
+        .map_err(ReleasyCoreError::FailedToSerializeEventToJSON)
+    }
+
+    /// Returns the delivery with the ref of `workflow-dispatch` resolved, looking up the default
+    /// branch of the target repo if no ref is declared.
+    pub(crate) async fn with_resolved_ref(
+        &self,
+        target_repo: &Repo,
+        github_token: &str,
+    ) -> Result<Delivery, ReleasyCoreError> {
+        match self {
+            Delivery::WorkflowDispatch {
+                workflow,
+                git_ref: None,
+            } => Ok(Delivery::WorkflowDispatch {
+                workflow: workflow.clone(),
+                git_ref: Some(default_branch(target_repo, github_token).await?),
+            }),
+            delivery => Ok(delivery.clone()),
+        }
+    }
+}
+
+/// Details of a repo as reported by the GitHub API.
+#[derive(Deserialize)]
+struct RepoInfo {
+    default_branch: String,
+}
+
+/// Returns the default branch of the repo as reported by the GitHub API.
+async fn default_branch(repo: &Repo, github_token: &str) -> Result<String, ReleasyCoreError> {
+    let info: RepoInfo = send(
+        reqwest::Method::GET,
+        repo_url(repo),
+        None,
+        github_token,
+        repo,
+    )
+    .await?;
+    Ok(info.default_branch)
+}
+
+/// Returns the URL of the GitHub API endpoint of the repo.
+fn repo_url(repo: &Repo) -> String {
+    format!(
+        "{}/repos/{}/{}",
+        github_api_url(),
+        repo.owner(),
+        repo.name()
+    )
+}
+
+#[derive(Serialize)]
+struct WorkflowDispatchRequest {
+    #[serde(rename = "ref")]
+    git_ref: String,
+    inputs: BTreeMap<&'static str, String>,
+}
+
+/// Runs a workflow of the repo on the given ref through `workflow_dispatch`, without any inputs.
+///
+/// Used to re-run the CI of a branch without pushing a commit to it.
+pub async fn run_workflow(
+    repo: &Repo,
+    workflow: &str,
+    git_ref: &str,
+    github_token: &str,
+) -> Result<(), ReleasyCoreError> {
+    let delivery = Delivery::WorkflowDispatch {
+        workflow: workflow.to_string(),
+        git_ref: Some(git_ref.to_string()),
+    };
+    let request = WorkflowDispatchRequest {
+        git_ref: git_ref.to_string(),
+        inputs: BTreeMap::new(),
+    };
+    let body =
+        serde_json::to_string(&request).map_err(ReleasyCoreError::FailedToSerializeEventToJSON)?;
+    post_to_github(delivery.request_url(repo), body, github_token, repo).await
+}
+
+/// Inputs of a workflow triggered with `workflow-dispatch` delivery.
+///
+/// Workflow inputs can only be strings, so the client payload is passed as a JSON string which
+/// keeps the event intact. The receiving workflow should declare both inputs:
+///
+/// ```yaml
+/// on:
+///   workflow_dispatch:
+///     inputs:
+///       event_type:
+///         required: true
+///       client_payload:
+///         required: true
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct WorkflowInputs {
+    pub event_type: String,
+    pub client_payload: String,
+}
+
+impl WorkflowInputs {
+    pub const EVENT_TYPE_INPUT: &'static str = "event_type";
+    pub const CLIENT_PAYLOAD_INPUT: &'static str = "client_payload";
+
+    fn into_map(self) -> BTreeMap<&'static str, String> {
+        BTreeMap::from([
+            (Self::EVENT_TYPE_INPUT, self.event_type),
+            (Self::CLIENT_PAYLOAD_INPUT, self.client_payload),
+        ])
+    }
+}
+
+impl TryFrom<&Event> for WorkflowInputs {
+    type Error = ReleasyCoreError;
+
+    fn try_from(event: &Event) -> Result<Self, Self::Error> {
+        let client_payload = serde_json::to_string(event.client_payload())
+            .map_err(ReleasyCoreError::FailedToSerializeEventToJSON)?;
+        Ok(Self {
+            event_type: event.event_type().to_string(),
+            client_payload,
+        })
+    }
+}
+
+impl TryFrom<WorkflowInputs> for Event {
+    type Error = ReleasyCoreError;
+
+    fn try_from(inputs: WorkflowInputs) -> Result<Self, Self::Error> {
+        let event_type = inputs.event_type.parse()?;
+        let client_payload = serde_json::from_str::<ClientPayload>(&inputs.client_payload)
+            .map_err(ReleasyCoreError::FailedToDeserializeClientPayload)?;
+        Ok(Event::new(event_type, client_payload))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Delivery, WorkflowInputs};
+    use crate::{
+        event::{ClientPayload, Event, EventDetails, EventType},
+        repo::Repo,
+    };
+
+    #[test]
+    fn workflow_inputs_round_trip() {
+        let repo = Repo::new("sway".to_string(), "FuelLabs".to_string());
+        let details = EventDetails::new(Some("337d0eaa".to_string()), None)
+            .with_branch(Some("release/v0.46".to_string()))
+            .with_subject(Some("Fix \"quoted\" subject\nwith newline".to_string()))
+            .with_changed_paths(Some(vec!["sway-lib-std/src/lib.sw".to_string()]));
+        let event = Event::new(
+            EventType::Custom("docs-published".to_string()),
+            ClientPayload::new(repo.clone(), details),
+        );
+
+        let inputs = WorkflowInputs::try_from(&event).unwrap();
+        assert_eq!(inputs.event_type, "docs-published");
+        assert_eq!(Event::try_from(inputs).unwrap(), event);
+
+        let delivery = Delivery::WorkflowDispatch {
+            workflow: "releasy.yml".to_string(),
+            git_ref: None,
+        };
+        assert_eq!(
+            delivery.request_url(&repo),
+            "https://api.github.com/repos/FuelLabs/sway/actions/workflows/releasy.yml/dispatches"
+        );
+        // The ref is looked up from the GitHub API if it is not declared.
+        assert!(delivery.request_body(&event).is_err());
+        let delivery = Delivery::WorkflowDispatch {
+            workflow: "releasy.yml".to_string(),
+            git_ref: Some("main".to_string()),
+        };
+        let body: serde_json::Value =
+            serde_json::from_str(&delivery.request_body(&event).unwrap()).unwrap();
+        assert_eq!(body["ref"], "main");
+        assert_eq!(body["inputs"]["event_type"], "docs-published");
+    }
+}
@@ -16,8 +16,16 @@ pub enum ReleasyCoreError {
     FailedToParseHeader(String),
     #[error("failed to serialize event to a JSON string, reason: `{0}`")]
     FailedToSerializeEventToJSON(serde_json::Error),
+    #[error("failed to deserialize client payload from JSON, reason: `{0}`")]
+    FailedToDeserializeClientPayload(serde_json::Error),
     #[error("failed to send dispatch request to {0}, reason: `{1}`")]
     FailedToSendDispatchRequest(repo::Repo, reqwest::Error),
-    #[error("failed to convert str (`{0}`) to `EventType`, possible values are: [`new-commit`, `new-release`]")]
+    #[error("failed to send request to the GitHub API for {0}, reason: `{1}`")]
+    FailedToSendGithubRequest(repo::Repo, reqwest::Error),
+    #[error("failed to deserialize the response of the GitHub API, reason: `{0}`")]
+    FailedToDeserializeGithubResponse(serde_json::Error),
+    #[error("no ref to run workflow `{0}` on")]
+    MissingWorkflowRef(String),
+    #[error("failed to convert str (`{0}`) to `EventType`, possible values are: [`new-commit-to-dependency`, `new-commit-to-self`, `new-release`, `new-pull-request`] or the name of a custom event")]
     FailedToConvertStrToEventType(String),
 }
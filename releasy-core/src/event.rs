@@ -1,6 +1,6 @@
-use crate::{error::ReleasyCoreError, repo::Repo};
+use crate::{delivery::Delivery, error::ReleasyCoreError, repo::Repo};
 use reqwest::header::{ACCEPT, AUTHORIZATION, USER_AGENT};
-use serde::{Deserialize, Serialize};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
 use std::{fmt::Display, str::FromStr};
 
 /// An event to be emitted.
@@ -20,16 +20,160 @@ pub struct ClientPayload {
 pub struct EventDetails {
     commit_hash: Option<String>,
     release_tag: Option<String>,
+    /// Branch of the source repo this event originated from.
+    ///
+    /// Older emitters do not send this field, in which case the default branch is assumed.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    branch: Option<String>,
+    /// Author of the commit, as `name <email>`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    author: Option<String>,
+    /// Subject line of the commit message.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    subject: Option<String>,
+    /// Commit timestamp in RFC 3339 format.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    timestamp: Option<String>,
+    /// Previous commit processed by the emitter, the event covers the commits after it.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    base_commit_hash: Option<String>,
+    /// Paths changed by the commits covered by the event.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    changed_paths: Option<Vec<String>>,
+    /// Set if `changed_paths` is cut short to fit into the payload limits.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    changed_paths_truncated: bool,
+    /// Pull request of the source repo this event originated from, for `new-pull-request`
+    /// events. `commit_hash` is the head commit of the pull request.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pull_request: Option<PullRequest>,
+}
+
+/// A pull request of the source repo of an event.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PullRequest {
+    number: u64,
+    /// Set once the pull request is closed or merged.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    closed: bool,
+}
+
+impl PullRequest {
+    pub fn new(number: u64) -> Self {
+        Self {
+            number,
+            closed: false,
+        }
+    }
+
+    /// Sets whether the pull request is closed.
+    pub fn with_closed(mut self, closed: bool) -> Self {
+        self.closed = closed;
+        self
+    }
+
+    pub fn number(&self) -> u64 {
+        self.number
+    }
+
+    pub fn is_closed(&self) -> bool {
+        self.closed
+    }
 }
 
 impl EventDetails {
+    /// Commits with this marker in their subject are not propagated.
+    pub const SKIP_MARKER: &'static str = "[skip releasy]";
+    /// Maximum number of changed paths carried by an event.
+    pub const MAX_CHANGED_PATHS: usize = 256;
+    /// Maximum total length of changed paths carried by an event, in bytes.
+    ///
+    /// Keeps the payload well within the size accepted by GitHub for dispatch events.
+    pub const MAX_CHANGED_PATHS_LEN: usize = 16 * 1024;
+    /// Maximum length of the commit subject carried by an event, in bytes.
+    pub const MAX_SUBJECT_LEN: usize = 256;
+
     pub fn new(commit_hash: Option<String>, release_tag: Option<String>) -> Self {
         Self {
             commit_hash,
             release_tag,
+            branch: None,
+            author: None,
+            subject: None,
+            timestamp: None,
+            base_commit_hash: None,
+            changed_paths: None,
+            changed_paths_truncated: false,
+            pull_request: None,
         }
     }
 
+    /// Sets the branch of the source repo this event originated from.
+    pub fn with_branch(mut self, branch: Option<String>) -> Self {
+        self.branch = branch;
+        self
+    }
+
+    /// Sets the author of the commit.
+    pub fn with_author(mut self, author: Option<String>) -> Self {
+        self.author = author;
+        self
+    }
+
+    /// Sets the subject of the commit, truncating it to `MAX_SUBJECT_LEN`.
+    pub fn with_subject(mut self, subject: Option<String>) -> Self {
+        self.subject = subject.map(|mut subject| {
+            if subject.len() > Self::MAX_SUBJECT_LEN {
+                let mut end = Self::MAX_SUBJECT_LEN;
+                while !subject.is_char_boundary(end) {
+                    end -= 1;
+                }
+                subject.truncate(end);
+            }
+            subject
+        });
+        self
+    }
+
+    /// Sets the timestamp of the commit.
+    pub fn with_timestamp(mut self, timestamp: Option<String>) -> Self {
+        self.timestamp = timestamp;
+        self
+    }
+
+    /// Sets the previous commit processed by the emitter.
+    pub fn with_base_commit_hash(mut self, base_commit_hash: Option<String>) -> Self {
+        self.base_commit_hash = base_commit_hash;
+        self
+    }
+
+    /// Sets the changed paths, dropping the ones exceeding `MAX_CHANGED_PATHS` or
+    /// `MAX_CHANGED_PATHS_LEN`.
+    pub fn with_changed_paths(mut self, changed_paths: Option<Vec<String>>) -> Self {
+        self.changed_paths_truncated = false;
+        self.changed_paths = changed_paths.map(|changed_paths| {
+            let mut total_len = 0;
+            let mut kept = vec![];
+            for path in changed_paths {
+                total_len += path.len();
+                if kept.len() == Self::MAX_CHANGED_PATHS || total_len > Self::MAX_CHANGED_PATHS_LEN
+                {
+                    self.changed_paths_truncated = true;
+                    break;
+                }
+                kept.push(path);
+            }
+            kept
+        });
+        self
+    }
+
+    /// Sets the pull request this event originated from.
+    pub fn with_pull_request(mut self, pull_request: Option<PullRequest>) -> Self {
+        self.pull_request = pull_request;
+        self
+    }
+
     pub fn commit_hash(&self) -> Option<&String> {
         self.commit_hash.as_ref()
     }
@@ -37,6 +181,57 @@ impl EventDetails {
     pub fn release_tag(&self) -> Option<&String> {
         self.release_tag.as_ref()
     }
+
+    pub fn branch(&self) -> Option<&String> {
+        self.branch.as_ref()
+    }
+
+    pub fn author(&self) -> Option<&String> {
+        self.author.as_ref()
+    }
+
+    pub fn subject(&self) -> Option<&String> {
+        self.subject.as_ref()
+    }
+
+    pub fn timestamp(&self) -> Option<&String> {
+        self.timestamp.as_ref()
+    }
+
+    pub fn base_commit_hash(&self) -> Option<&String> {
+        self.base_commit_hash.as_ref()
+    }
+
+    pub fn pull_request(&self) -> Option<&PullRequest> {
+        self.pull_request.as_ref()
+    }
+
+    /// Returns the `<base>..<commit>` range covered by the event, if both ends are known.
+    pub fn compare_range(&self) -> Option<String> {
+        Some(format!(
+            "{}..{}",
+            self.base_commit_hash.as_ref()?,
+            self.commit_hash.as_ref()?
+        ))
+    }
+
+    /// Returns the complete list of changed paths.
+    ///
+    /// Returns `None` if the list is not sent or it is truncated, as path filters cannot be
+    /// applied reliably in either case.
+    pub fn changed_paths(&self) -> Option<&[String]> {
+        if self.changed_paths_truncated {
+            return None;
+        }
+        self.changed_paths.as_deref()
+    }
+
+    /// Returns true if the commit is marked with `SKIP_MARKER` and should not be propagated.
+    pub fn is_skipped(&self) -> bool {
+        self.subject
+            .as_deref()
+            .is_some_and(|subject| subject.contains(Self::SKIP_MARKER))
+    }
 }
 
 impl ClientPayload {
@@ -66,51 +261,24 @@ impl Event {
 
     /// Send this event to a target repo using github API.
     pub async fn send_to_repo(&self, target_repo: &Repo) -> Result<(), ReleasyCoreError> {
+        self.deliver_to_repo(target_repo, &Delivery::default())
+            .await
+    }
+
+    /// Send this event to a target repo using github API, as described by the given delivery.
+    pub async fn deliver_to_repo(
+        &self,
+        target_repo: &Repo,
+        delivery: &Delivery,
+    ) -> Result<(), ReleasyCoreError> {
         let github_token = std::env::var("DISPATCH_TOKEN")
             .map_err(|_| ReleasyCoreError::MissingDispatchTokenEnvVariable)?;
-        let client = reqwest::Client::builder()
-            .build()
-            .map_err(|_| ReleasyCoreError::FailedToBuildReqwestClient)?;
-        let request_url = format!(
-            "https://api.github.com/repos/{}/{}/dispatches",
-            target_repo.owner(),
-            target_repo.name()
-        );
-
-        let bearer_token = format!("Bearer {github_token}");
-        let mut headers = reqwest::header::HeaderMap::new();
-        headers.insert(
-            AUTHORIZATION,
-            bearer_token
-                .parse()
-                .map_err(|_| ReleasyCoreError::FailedToParseHeader(bearer_token))?,
-        );
-        headers.insert(
-            ACCEPT,
-            Self::ACCEPT
-                .parse()
-                .map_err(|_| ReleasyCoreError::FailedToParseHeader(Self::ACCEPT.to_string()))?,
-        );
-        headers.insert(
-            USER_AGENT,
-            Self::USER_AGENT
-                .parse()
-                .map_err(|_| ReleasyCoreError::FailedToParseHeader(Self::USER_AGENT.to_string()))?,
-        );
-
-        let json_str =
-            serde_json::to_string(self).map_err(ReleasyCoreError::FailedToSerializeEventToJSON)?;
-
-        let request = client
-            .request(reqwest::Method::POST, request_url)
-            .headers(headers)
-            .body(json_str);
-
-        request
-            .send()
-            .await
-            .map_err(|e| ReleasyCoreError::FailedToSendDispatchRequest(target_repo.clone(), e))?;
-        Ok(())
+        let delivery = delivery
+            .with_resolved_ref(target_repo, &github_token)
+            .await?;
+        let request_url = delivery.request_url(target_repo);
+        let json_str = delivery.request_body(self)?;
+        post_to_github(request_url, json_str, &github_token, target_repo).await
     }
 
     pub fn event_type(&self) -> &EventType {
@@ -122,39 +290,226 @@ impl Event {
     }
 }
 
+/// Environment variable overriding the base URL of the GitHub API, e.g. for GitHub Enterprise or a
+/// local mock server. Set by GitHub Actions.
+pub const GITHUB_API_URL_ENV: &str = "GITHUB_API_URL";
+
+/// Returns the base URL of the GitHub API, without a trailing slash.
+pub(crate) fn github_api_url() -> String {
+    std::env::var(GITHUB_API_URL_ENV)
+        .map(|url| url.trim_end_matches('/').to_string())
+        .unwrap_or_else(|_| "https://api.github.com".to_string())
+}
+
+/// Sends a POST request to the GitHub API on behalf of the target repo, failing on error statuses.
+pub(crate) async fn post_to_github(
+    request_url: String,
+    body: String,
+    github_token: &str,
+    target_repo: &Repo,
+) -> Result<(), ReleasyCoreError> {
+    github_request(reqwest::Method::POST, request_url, github_token)?
+        .body(body)
+        .send()
+        .await
+        .and_then(|response| response.error_for_status())
+        .map_err(|e| ReleasyCoreError::FailedToSendDispatchRequest(target_repo.clone(), e))?;
+    Ok(())
+}
+
+/// Sends a request to the GitHub API on behalf of the repo and parses the JSON response.
+pub(crate) async fn send<T: DeserializeOwned>(
+    method: reqwest::Method,
+    request_url: String,
+    body: Option<String>,
+    github_token: &str,
+    repo: &Repo,
+) -> Result<T, ReleasyCoreError> {
+    let mut request = github_request(method, request_url, github_token)?;
+    if let Some(body) = body {
+        request = request.body(body);
+    }
+    let response = request
+        .send()
+        .await
+        .and_then(|response| response.error_for_status())
+        .map_err(|e| ReleasyCoreError::FailedToSendGithubRequest(repo.clone(), e))?;
+    let contents = response
+        .text()
+        .await
+        .map_err(|e| ReleasyCoreError::FailedToSendGithubRequest(repo.clone(), e))?;
+    serde_json::from_str(&contents).map_err(ReleasyCoreError::FailedToDeserializeGithubResponse)
+}
+
+/// Builds a request to the GitHub API authenticated with the given token.
+pub(crate) fn github_request(
+    method: reqwest::Method,
+    request_url: String,
+    github_token: &str,
+) -> Result<reqwest::RequestBuilder, ReleasyCoreError> {
+    let client = reqwest::Client::builder()
+        .build()
+        .map_err(|_| ReleasyCoreError::FailedToBuildReqwestClient)?;
+
+    let bearer_token = format!("Bearer {github_token}");
+    let mut headers = reqwest::header::HeaderMap::new();
+    headers.insert(
+        AUTHORIZATION,
+        bearer_token
+            .parse()
+            .map_err(|_| ReleasyCoreError::FailedToParseHeader(bearer_token))?,
+    );
+    headers.insert(
+        ACCEPT,
+        Event::ACCEPT
+            .parse()
+            .map_err(|_| ReleasyCoreError::FailedToParseHeader(Event::ACCEPT.to_string()))?,
+    );
+    headers.insert(
+        USER_AGENT,
+        Event::USER_AGENT
+            .parse()
+            .map_err(|_| ReleasyCoreError::FailedToParseHeader(Event::USER_AGENT.to_string()))?,
+    );
+
+    Ok(client.request(method, request_url).headers(headers))
+}
+
 /// Possible event types.
-#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
-#[serde(rename_all = "kebab-case")]
+///
+/// Event types other than the builtin ones are parsed as `Custom`, so that handlers keep working
+/// with events introduced by newer emitters or declared in the manifest. Names of custom events
+/// are only validated where the manifest declares them, handlers skip the ones they do not know.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[serde(try_from = "String", into = "String")]
 pub enum EventType {
     NewCommitToDependency,
     NewCommitToSelf,
     NewRelease,
+    /// A pull request of an upstream repo is opened, updated or closed.
+    NewPullRequest,
+    Custom(String),
+}
+
+impl EventType {
+    /// Returns true if this is not one of the builtin event types.
+    pub fn is_custom(&self) -> bool {
+        matches!(self, EventType::Custom(_))
+    }
+
+    /// Returns true if the name can be used for declaring a custom event, which should consist of
+    /// lowercase letters, digits and dashes.
+    pub fn is_valid_custom_name(name: &str) -> bool {
+        !name.is_empty()
+            && name
+                .chars()
+                .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-')
+    }
 }
 
 impl FromStr for EventType {
     type Err = ReleasyCoreError;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        if s == "new-commit-to-dependency" {
-            Ok(Self::NewCommitToDependency)
-        } else if s == "new-commit-to-self" {
-            Ok(Self::NewCommitToSelf)
-        } else if s == "new-release" {
-            Ok(Self::NewRelease)
-        } else {
-            return Err(ReleasyCoreError::FailedToConvertStrToEventType(
+        match s {
+            "new-commit-to-dependency" => Ok(Self::NewCommitToDependency),
+            "new-commit-to-self" => Ok(Self::NewCommitToSelf),
+            "new-release" => Ok(Self::NewRelease),
+            "new-pull-request" => Ok(Self::NewPullRequest),
+            "" => Err(ReleasyCoreError::FailedToConvertStrToEventType(
                 s.to_string(),
-            ));
+            )),
+            custom => Ok(Self::Custom(custom.to_string())),
         }
     }
 }
 
+impl TryFrom<String> for EventType {
+    type Error = ReleasyCoreError;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        value.parse()
+    }
+}
+
+impl From<EventType> for String {
+    fn from(value: EventType) -> Self {
+        value.to_string()
+    }
+}
+
 impl Display for EventType {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             EventType::NewCommitToDependency => write!(f, "new-commit-to-dependency"),
             EventType::NewCommitToSelf => write!(f, "new-commit-to-self"),
             EventType::NewRelease => write!(f, "new-release"),
+            EventType::NewPullRequest => write!(f, "new-pull-request"),
+            EventType::Custom(name) => write!(f, "{name}"),
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::EventDetails;
+
+    #[test]
+    fn truncate_changed_paths_and_subject() {
+        let changed_paths = (0..EventDetails::MAX_CHANGED_PATHS + 1)
+            .map(|ix| format!("src/{ix}.rs"))
+            .collect();
+        let details = EventDetails::new(None, None)
+            .with_changed_paths(Some(changed_paths))
+            .with_subject(Some("é".repeat(EventDetails::MAX_SUBJECT_LEN)));
+
+        // Truncated paths cannot be used for filtering, but they are still sent for reference.
+        assert_eq!(details.changed_paths(), None);
+        let json = serde_json::to_value(&details).unwrap();
+        assert_eq!(
+            json["changed_paths"].as_array().unwrap().len(),
+            EventDetails::MAX_CHANGED_PATHS
+        );
+        assert_eq!(json["changed_paths_truncated"], true);
+        assert_eq!(
+            details.subject().unwrap().len(),
+            EventDetails::MAX_SUBJECT_LEN
+        );
+    }
+
+    #[test]
+    fn deserialize_details_from_older_emitters() {
+        let details = r#"{ "commit_hash": "337d0eaa", "release_tag": null }"#;
+        let details = serde_json::from_str::<EventDetails>(details).unwrap();
+        assert_eq!(
+            details,
+            EventDetails::new(Some("337d0eaa".to_string()), None)
+        );
+        assert_eq!(details.changed_paths(), None);
+        assert!(!details.is_skipped());
+    }
+
+    #[test]
+    fn parse_custom_event_types() {
+        use super::EventType;
+
+        let event_type = serde_json::from_str::<EventType>(r#""docs-published""#).unwrap();
+        assert_eq!(event_type, EventType::Custom("docs-published".to_string()));
+        assert_eq!(
+            serde_json::to_string(&event_type).unwrap(),
+            r#""docs-published""#
+        );
+        assert_eq!(
+            serde_json::from_str::<EventType>(r#""new-release""#).unwrap(),
+            EventType::NewRelease
+        );
+        // Names of events introduced by newer emitters are kept as they are.
+        assert_eq!(
+            serde_json::from_str::<EventType>(r#""Audit_Requested""#).unwrap(),
+            EventType::Custom("Audit_Requested".to_string())
+        );
+        assert!(!EventType::is_valid_custom_name("Audit_Requested"));
+        assert!(EventType::is_valid_custom_name("docs-published"));
+        assert!("".parse::<EventType>().is_err());
+    }
+}
@@ -0,0 +1,179 @@
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    error::ReleasyCoreError,
+    event::{github_api_url, send},
+    repo::Repo,
+};
+
+/// A pull request to be opened by releasy, e.g. a version bump of a release train.
+#[derive(Serialize, Clone, Debug, PartialEq, Eq)]
+pub struct NewPullRequest {
+    title: String,
+    /// Branch containing the changes.
+    head: String,
+    /// Branch the changes are merged into.
+    base: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    body: Option<String>,
+}
+
+impl NewPullRequest {
+    pub fn new(title: String, head: String, base: String) -> Self {
+        Self {
+            title,
+            head,
+            base,
+            body: None,
+        }
+    }
+
+    /// Sets the description of the pull request.
+    pub fn with_body(mut self, body: Option<String>) -> Self {
+        self.body = body;
+        self
+    }
+
+    /// Opens this pull request on the given repo.
+    pub async fn open(
+        &self,
+        repo: &Repo,
+        github_token: &str,
+    ) -> Result<PullRequestInfo, ReleasyCoreError> {
+        let body =
+            serde_json::to_string(self).map_err(ReleasyCoreError::FailedToSerializeEventToJSON)?;
+        send(
+            reqwest::Method::POST,
+            pulls_url(repo),
+            Some(body),
+            github_token,
+            repo,
+        )
+        .await
+    }
+}
+
+/// A pull request as reported by the GitHub API.
+#[derive(Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct PullRequestInfo {
+    number: u64,
+    html_url: String,
+    /// Either `open` or `closed`, merged pull requests are closed.
+    state: String,
+    merged_at: Option<String>,
+    merge_commit_sha: Option<String>,
+}
+
+impl PullRequestInfo {
+    pub fn number(&self) -> u64 {
+        self.number
+    }
+
+    pub fn url(&self) -> &str {
+        &self.html_url
+    }
+
+    pub fn is_merged(&self) -> bool {
+        self.merged_at.is_some()
+    }
+
+    /// Returns true if the pull request is closed without being merged.
+    pub fn is_abandoned(&self) -> bool {
+        self.state == "closed" && !self.is_merged()
+    }
+
+    /// Returns the commit the pull request is merged with, once it is merged.
+    pub fn merge_commit(&self) -> Option<&str> {
+        self.merge_commit_sha
+            .as_deref()
+            .filter(|_| self.is_merged())
+    }
+}
+
+/// Returns the latest pull request of the given branch of the repo, whatever its state.
+pub async fn find_pull_request(
+    repo: &Repo,
+    head_branch: &str,
+    github_token: &str,
+) -> Result<Option<PullRequestInfo>, ReleasyCoreError> {
+    let request_url = format!(
+        "{}?head={}:{head_branch}&state=all",
+        pulls_url(repo),
+        repo.owner()
+    );
+    let pull_requests: Vec<PullRequestInfo> =
+        send(reqwest::Method::GET, request_url, None, github_token, repo).await?;
+    Ok(pull_requests.into_iter().next())
+}
+
+/// Returns the pull request of the repo with the given number.
+pub async fn get_pull_request(
+    repo: &Repo,
+    number: u64,
+    github_token: &str,
+) -> Result<PullRequestInfo, ReleasyCoreError> {
+    let request_url = format!("{}/{number}", pulls_url(repo));
+    send(reqwest::Method::GET, request_url, None, github_token, repo).await
+}
+
+/// Returns the URL of the GitHub API endpoint listing the pull requests of the repo.
+fn pulls_url(repo: &Repo) -> String {
+    format!(
+        "{}/repos/{}/{}/pulls",
+        github_api_url(),
+        repo.owner(),
+        repo.name()
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{pulls_url, NewPullRequest, PullRequestInfo};
+    use crate::repo::Repo;
+
+    #[test]
+    fn pull_request_requests() {
+        let fuel_core = Repo::new("fuel-core".to_string(), "FuelLabs".to_string());
+        assert_eq!(
+            pulls_url(&fuel_core),
+            "https://api.github.com/repos/FuelLabs/fuel-core/pulls"
+        );
+        let pull_request = NewPullRequest::new(
+            "Bump version to 0.21.0".to_string(),
+            "release-train/v0.21.0".to_string(),
+            "master".to_string(),
+        );
+        assert_eq!(
+            serde_json::to_string(&pull_request).unwrap(),
+            r#"{"title":"Bump version to 0.21.0","head":"release-train/v0.21.0","base":"master"}"#
+        );
+
+        let merged: PullRequestInfo = serde_json::from_str(
+            r#"{
+                "number": 1432,
+                "html_url": "https://github.com/FuelLabs/fuel-core/pull/1432",
+                "state": "closed",
+                "merged_at": "2023-10-10T10:00:00Z",
+                "merge_commit_sha": "9e3b1c7a",
+                "title": "Bump version to 0.21.0"
+            }"#,
+        )
+        .unwrap();
+        assert!(merged.is_merged());
+        assert!(!merged.is_abandoned());
+        assert_eq!(merged.merge_commit(), Some("9e3b1c7a"));
+
+        let open: PullRequestInfo = serde_json::from_str(
+            r#"{
+                "number": 1432,
+                "html_url": "https://github.com/FuelLabs/fuel-core/pull/1432",
+                "state": "open",
+                "merged_at": null,
+                "merge_commit_sha": "e1f2a3b4"
+            }"#,
+        )
+        .unwrap();
+        assert!(!open.is_merged());
+        assert_eq!(open.merge_commit(), None);
+    }
+}
@@ -0,0 +1,275 @@
+//! Detection of the event context from the CI environment and the local git checkout.
+use std::process::Command;
+
+use releasy_core::{
+    event::{EventDetails, PullRequest},
+    repo::Repo,
+};
+
+/// Details of the event detected from the environment, used when they are not provided
+/// explicitly.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct EventContext {
+    repo: Option<Repo>,
+    commit_hash: Option<String>,
+    release_tag: Option<String>,
+    branch: Option<String>,
+    base_commit_hash: Option<String>,
+    pull_request: Option<PullRequest>,
+}
+
+impl EventContext {
+    /// Detects the context from GitHub Actions variables if they are present, falling back to the
+    /// git checkout in the current dir.
+    pub fn detect() -> Self {
+        Self::detect_with(|name| std::env::var(name).ok(), git_output)
+    }
+
+    /// Detects the context using the given environment and git lookups.
+    fn detect_with(
+        env: impl Fn(&str) -> Option<String>,
+        git: impl Fn(&[&str]) -> Option<String>,
+    ) -> Self {
+        let repo = env("GITHUB_REPOSITORY")
+            .and_then(|repository| repo_from_slug(&repository))
+            .or_else(|| git(&["remote", "get-url", "origin"]).and_then(|url| repo_from_url(&url)));
+        let payload = env("GITHUB_EVENT_PATH")
+            .and_then(|path| std::fs::read_to_string(path).ok())
+            .and_then(|payload| serde_json::from_str::<serde_json::Value>(&payload).ok());
+        // Pull request events are run on a merge commit, the head of the pull request is the
+        // commit downstream repos should build against.
+        let pull_request = payload
+            .as_ref()
+            .and_then(|payload| payload.get("pull_request"));
+        let commit_hash = pull_request
+            .and_then(|pull_request| pull_request.pointer("/head/sha")?.as_str())
+            .map(str::to_string)
+            .or_else(|| env("GITHUB_SHA"))
+            .or_else(|| git(&["rev-parse", "HEAD"]));
+        let pull_request = pull_request.and_then(|pull_request| {
+            let number = pull_request.get("number")?.as_u64()?;
+            let closed = pull_request.get("state")?.as_str() == Some("closed");
+            Some(PullRequest::new(number).with_closed(closed))
+        });
+        let (branch, release_tag) = match env("GITHUB_REF") {
+            Some(github_ref) => match github_ref.strip_prefix("refs/tags/") {
+                Some(tag) => (None, Some(tag.to_string())),
+                None => (
+                    github_ref.strip_prefix("refs/heads/").map(str::to_string),
+                    None,
+                ),
+            },
+            None => (
+                git(&["rev-parse", "--abbrev-ref", "HEAD"]).filter(|branch| branch != "HEAD"),
+                git(&["describe", "--tags", "--exact-match", "HEAD"]),
+            ),
+        };
+        // Push events carry the previous head of the branch, which is all zeros for new branches.
+        let base_commit_hash = payload
+            .as_ref()
+            .and_then(|payload| payload.get("before")?.as_str().map(str::to_string))
+            .filter(|before| before.chars().any(|c| c != '0'));
+        Self {
+            repo,
+            commit_hash,
+            release_tag,
+            branch,
+            base_commit_hash,
+            pull_request,
+        }
+    }
+
+    /// Returns the repo the event originates from, read from `GITHUB_REPOSITORY` or the origin
+    /// remote.
+    pub fn repo(&self) -> Option<&Repo> {
+        self.repo.as_ref()
+    }
+
+    pub fn commit_hash(&self) -> Option<&String> {
+        self.commit_hash.as_ref()
+    }
+
+    pub fn release_tag(&self) -> Option<&String> {
+        self.release_tag.as_ref()
+    }
+
+    pub fn branch(&self) -> Option<&String> {
+        self.branch.as_ref()
+    }
+
+    /// Returns the previous head of the branch, read from the push event payload of GitHub
+    /// Actions.
+    pub fn base_commit_hash(&self) -> Option<&String> {
+        self.base_commit_hash.as_ref()
+    }
+
+    /// Returns the pull request read from the pull request event payload of GitHub Actions.
+    pub fn pull_request(&self) -> Option<&PullRequest> {
+        self.pull_request.as_ref()
+    }
+}
+
+/// Fills the author, subject, timestamp and changed paths of the event commit from the local
+/// checkout.
+///
+/// Details are left as is if the commit is not available locally. Changed paths cover the
+/// `<base>..<commit>` range if the base commit is known, and the commit alone otherwise.
+pub fn with_commit_metadata(details: EventDetails) -> EventDetails {
+    let Some(commit_hash) = details.commit_hash().cloned() else {
+        return details;
+    };
+    let Some(metadata) = git_output(&[
+        "show",
+        "-s",
+        "--format=%an <%ae>%x00%s%x00%cI",
+        &commit_hash,
+    ]) else {
+        println!("WARNING: cannot read commit {commit_hash} from the local checkout");
+        return details;
+    };
+    let mut fields = metadata.splitn(3, '\0').map(str::to_string);
+    let (author, subject, timestamp) = (fields.next(), fields.next(), fields.next());
+
+    let changed_paths = match details.compare_range() {
+        Some(range) => git_output(&["diff", "--name-only", &range]),
+        None => git_output(&[
+            "diff-tree",
+            "--no-commit-id",
+            "--name-only",
+            "-r",
+            "-m",
+            "--root",
+            &commit_hash,
+        ]),
+    }
+    .map(|paths| paths.lines().map(str::to_string).collect());
+
+    details
+        .with_author(author)
+        .with_subject(subject)
+        .with_timestamp(timestamp)
+        .with_changed_paths(changed_paths)
+}
+
+/// Resolves a revision of the local checkout, e.g. a branch or a tag, to its commit hash.
+pub fn resolve_commit(rev: &str) -> Option<String> {
+    git_output(&[
+        "rev-parse",
+        "--verify",
+        "--quiet",
+        &format!("{rev}^{{commit}}"),
+    ])
+}
+
+/// Parses an `<owner>/<name>` slug.
+fn repo_from_slug(slug: &str) -> Option<Repo> {
+    let (owner, name) = slug.trim_matches('/').split_once('/')?;
+    if owner.is_empty() || name.is_empty() || name.contains('/') {
+        return None;
+    }
+    Some(Repo::new(name.to_string(), owner.to_string()))
+}
+
+/// Parses the repo out of a remote URL, either in the `https://github.com/<owner>/<name>.git` or
+/// in the `git@github.com:<owner>/<name>.git` form.
+pub fn repo_from_url(url: &str) -> Option<Repo> {
+    let path = url.trim_end_matches('/').trim_end_matches(".git");
+    let path = match path.split_once("://") {
+        Some((_, rest)) => rest.split_once('/')?.1,
+        None => path.split_once(':')?.1,
+    };
+    let mut segments = path.rsplitn(3, '/');
+    let name = segments.next()?;
+    let owner = segments.next()?;
+    repo_from_slug(&format!("{owner}/{name}"))
+}
+
+fn git_output(args: &[&str]) -> Option<String> {
+    let output = Command::new("git").args(args).output().ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    let stdout = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    (!stdout.is_empty()).then_some(stdout)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{repo_from_url, EventContext};
+    use releasy_core::repo::Repo;
+
+    #[test]
+    fn parse_repo_from_remote_url() {
+        let sway = Some(Repo::new("sway".to_string(), "FuelLabs".to_string()));
+        assert_eq!(repo_from_url("https://github.com/FuelLabs/sway.git"), sway);
+        assert_eq!(repo_from_url("https://github.com/FuelLabs/sway/"), sway);
+        assert_eq!(repo_from_url("git@github.com:FuelLabs/sway.git"), sway);
+        assert_eq!(repo_from_url("/tmp/sway"), None);
+    }
+
+    #[test]
+    fn github_env_takes_precedence_over_git() {
+        let env = |name: &str| match name {
+            "GITHUB_REPOSITORY" => Some("FuelLabs/fuels-rs".to_string()),
+            "GITHUB_SHA" => Some("337d0eaa130dd18e9e347f83ab4fab76b3a6bd2a".to_string()),
+            "GITHUB_REF" => Some("refs/tags/v0.46.0".to_string()),
+            _ => None,
+        };
+        let git = |_: &[&str]| Some("from-git".to_string());
+        let context = EventContext::detect_with(env, git);
+
+        assert_eq!(context.repo().unwrap().name(), "fuels-rs");
+        assert_eq!(
+            context.commit_hash().unwrap(),
+            "337d0eaa130dd18e9e347f83ab4fab76b3a6bd2a"
+        );
+        assert_eq!(context.release_tag().unwrap(), "v0.46.0");
+        assert_eq!(context.branch(), None);
+    }
+
+    #[test]
+    fn detect_pull_request_from_event_payload() {
+        let event_path = std::env::temp_dir().join("releasy-pull-request-event.json");
+        std::fs::write(
+            &event_path,
+            r#"{
+                "action": "closed",
+                "before": "1a2b3c4d",
+                "pull_request": { "number": 1432, "state": "closed", "head": { "sha": "9e3b1c7a" } }
+            }"#,
+        )
+        .unwrap();
+        let env = |name: &str| match name {
+            "GITHUB_REPOSITORY" => Some("FuelLabs/fuel-core".to_string()),
+            "GITHUB_SHA" => Some("merge-commit".to_string()),
+            "GITHUB_REF" => Some("refs/pull/1432/merge".to_string()),
+            "GITHUB_EVENT_PATH" => Some(event_path.to_string_lossy().to_string()),
+            _ => None,
+        };
+        let context = EventContext::detect_with(env, |_| None);
+        let _ = std::fs::remove_file(&event_path);
+
+        assert_eq!(context.commit_hash().unwrap(), "9e3b1c7a");
+        assert_eq!(context.branch(), None);
+        let pull_request = context.pull_request().unwrap();
+        assert_eq!(pull_request.number(), 1432);
+        assert!(pull_request.is_closed());
+    }
+
+    #[test]
+    fn fall_back_to_git_checkout() {
+        let env = |_: &str| None;
+        let git = |args: &[&str]| match args {
+            ["remote", "get-url", "origin"] => Some("git@github.com:FuelLabs/sway.git".to_string()),
+            ["rev-parse", "HEAD"] => Some("9e3b1c7a".to_string()),
+            ["rev-parse", "--abbrev-ref", "HEAD"] => Some("master".to_string()),
+            _ => None,
+        };
+        let context = EventContext::detect_with(env, git);
+
+        assert_eq!(context.repo().unwrap().name(), "sway");
+        assert_eq!(context.commit_hash().unwrap(), "9e3b1c7a");
+        assert_eq!(context.branch().unwrap(), "master");
+        assert_eq!(context.release_tag(), None);
+    }
+}
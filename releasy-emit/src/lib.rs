@@ -0,0 +1,140 @@
+//! A library for emitting releasy events to the downstream repos of the current repo.
+pub mod context;
+pub mod outbox;
+pub mod report;
+pub mod target;
+
+use std::sync::Arc;
+
+use releasy_core::event::Event;
+use report::{EmitReport, TargetOutcome, TargetStatus};
+use target::Target;
+use tokio::{sync::Semaphore, task::JoinSet};
+
+/// Number of targets the event is sent to concurrently if not specified.
+pub const DEFAULT_CONCURRENCY: usize = 4;
+
+/// Sends the event to every target that is not skipped, with at most `concurrency` requests in
+/// flight.
+///
+/// A failure to notify a target does not prevent the remaining ones from being notified, the
+/// outcome of each target is collected into the returned report instead.
+pub async fn emit(
+    event: &Event,
+    targets: Vec<Target>,
+    concurrency: usize,
+) -> anyhow::Result<EmitReport> {
+    let mut outcomes = vec![];
+    let mut deliveries = vec![];
+    for target in targets {
+        let status = target
+            .skip_reason()
+            .map(|reason| TargetStatus::Skipped(reason.to_string()));
+        outcomes.push((target.repo().clone(), status));
+        deliveries.push(target.delivery().clone());
+    }
+
+    let semaphore = Arc::new(Semaphore::new(concurrency.max(1)));
+    let mut sends = JoinSet::new();
+    for (index, (target_repo, status)) in outcomes.iter().enumerate() {
+        if status.is_some() {
+            continue;
+        }
+        let semaphore = semaphore.clone();
+        let event = event.clone();
+        let target_repo = target_repo.clone();
+        let delivery = deliveries[index].clone();
+        sends.spawn(async move {
+            let _permit = semaphore.acquire_owned().await;
+            println!("Sending {} to {target_repo}", event.event_type());
+            let status = match event.deliver_to_repo(&target_repo, &delivery).await {
+                Ok(()) => TargetStatus::Sent,
+                Err(e) => TargetStatus::Failed(e.to_string()),
+            };
+            (index, status)
+        });
+    }
+    while let Some(sent) = sends.join_next().await {
+        let (index, status) = sent?;
+        outcomes[index].1 = Some(status);
+    }
+
+    let targets = outcomes
+        .into_iter()
+        .filter_map(|(repo, status)| Some(TargetOutcome::new(repo, status?)))
+        .collect();
+    Ok(EmitReport::new(targets))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::target::{resolve_targets, TargetSelection};
+    use releasy_core::event::{ClientPayload, Event, EventDetails, EventType};
+    use releasy_graph::{manifest::ManifestFile, plan::Plan};
+    use std::path::PathBuf;
+
+    const SWAY_WALLET_SDK_TEST_MANIFEST_FILE_NAME: &str = "repo-plan-sway-wallet-sdk.toml";
+
+    /// In this test we have:
+    ///  - forc-wallet
+    ///  - sway
+    ///  - fuels-rs
+    ///
+    /// repositories present. The dependency graph between them looks like:
+    ///
+    /// ```text
+    /// forc-wallet -> fuels-rs
+    /// sway -> fuels-rs
+    /// sway -> forc-wallet
+    /// ```
+    ///
+    /// This is a simple example and the circular dependency between the sdk and sway is omitted.
+    #[test]
+    fn sway_wallet_sdk_example_test_event_order() {
+        let test_manifest_file = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+            .join("tests")
+            .join(SWAY_WALLET_SDK_TEST_MANIFEST_FILE_NAME);
+
+        let manifest = ManifestFile::from_file(&test_manifest_file)
+            .unwrap()
+            .manifest();
+        let current_repo = manifest.current_repo().clone();
+        let plan = Plan::try_from_manifest(manifest).unwrap();
+
+        let target_repos = plan
+            .downstream_repos(current_repo)
+            .unwrap()
+            .map(|repo| repo.name())
+            .collect::<Vec<_>>();
+        let expected_target_repos = vec!["forc-wallet", "sway"];
+
+        assert_eq!(target_repos, expected_target_repos)
+    }
+
+    #[test]
+    fn resolve_targets_with_selection() {
+        let test_manifest_file = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+            .join("tests")
+            .join(SWAY_WALLET_SDK_TEST_MANIFEST_FILE_NAME);
+
+        let manifest = ManifestFile::from_file(&test_manifest_file)
+            .unwrap()
+            .manifest();
+        let current_repo = manifest.current_repo().clone();
+        let selection = TargetSelection::new(vec![], vec!["FuelLabs/sway".parse().unwrap()], false)
+            .with_groups(&manifest);
+        let plan = Plan::try_from_manifest(manifest).unwrap();
+        let details = EventDetails::new(None, None);
+        let client_payload = ClientPayload::new(current_repo.clone(), details);
+        let event = Event::new(EventType::NewCommitToDependency, client_payload);
+
+        let targets = resolve_targets(&event, &plan, &current_repo, &selection).unwrap();
+        let targets = targets
+            .iter()
+            .map(|target| (target.repo().name(), target.skip_reason()))
+            .collect::<Vec<_>>();
+        let expected_targets = vec![("forc-wallet", None), ("sway", Some("not selected"))];
+
+        assert_eq!(targets, expected_targets)
+    }
+}
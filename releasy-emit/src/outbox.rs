@@ -0,0 +1,518 @@
+//! Persistence of events that are not delivered yet, so that they can be replayed later.
+//!
+//! Events are written to the outbox before they are sent and removed once they are delivered, so
+//! that neither failed deliveries nor deliveries interrupted by a crash are lost.
+use std::{
+    io::Write,
+    path::PathBuf,
+    process::{Command, Stdio},
+    str::FromStr,
+};
+
+use releasy_core::{delivery::Delivery, event::Event, repo::Repo};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    emit,
+    report::{EmitReport, TargetStatus},
+    target::Target,
+};
+
+/// Default location of the outbox, relative to the current dir.
+pub const DEFAULT_OUTBOX_FILE_NAME: &str = ".releasy-outbox.json";
+
+/// Name of the file holding the outbox in the commits of a state ref.
+const OUTBOX_REF_FILE_NAME: &str = "outbox.json";
+
+/// Number of times the outbox ref is pushed before giving up on concurrent updates of the ref.
+const OUTBOX_PUSH_ATTEMPTS: usize = 3;
+
+/// Where the outbox is persisted.
+///
+/// Parsed from either a file path, or `git:<ref>` to keep the outbox in a ref of the local
+/// checkout which is pushed to `origin`, e.g. `git:refs/releasy/outbox`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum OutboxLocation {
+    File(PathBuf),
+    GitRef(String),
+}
+
+impl Default for OutboxLocation {
+    fn default() -> Self {
+        OutboxLocation::File(PathBuf::from(DEFAULT_OUTBOX_FILE_NAME))
+    }
+}
+
+impl FromStr for OutboxLocation {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.strip_prefix("git:") {
+            Some(git_ref) if git_ref.starts_with("refs/") => {
+                Ok(OutboxLocation::GitRef(git_ref.to_string()))
+            }
+            Some(git_ref) => anyhow::bail!("outbox ref `{git_ref}` should start with `refs/`"),
+            None if s.is_empty() => anyhow::bail!("outbox path should not be empty"),
+            None => Ok(OutboxLocation::File(PathBuf::from(s))),
+        }
+    }
+}
+
+/// An event waiting to be delivered to a target repo.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct OutboxEntry {
+    event: Event,
+    target: Repo,
+    #[serde(default)]
+    delivery: Delivery,
+    /// Number of failed attempts to deliver the event.
+    #[serde(default)]
+    attempts: u32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    last_error: Option<String>,
+}
+
+impl OutboxEntry {
+    pub fn new(event: Event, target: Repo, delivery: Delivery) -> Self {
+        Self {
+            event,
+            target,
+            delivery,
+            attempts: 0,
+            last_error: None,
+        }
+    }
+
+    pub fn event(&self) -> &Event {
+        &self.event
+    }
+
+    pub fn target(&self) -> &Repo {
+        &self.target
+    }
+
+    pub fn attempts(&self) -> u32 {
+        self.attempts
+    }
+
+    pub fn last_error(&self) -> Option<&str> {
+        self.last_error.as_deref()
+    }
+
+    fn is_same_delivery(&self, other: &OutboxEntry) -> bool {
+        self.event == other.event && self.target == other.target
+    }
+}
+
+/// Events waiting to be delivered, in the order they were emitted.
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, Eq)]
+pub struct Outbox {
+    entries: Vec<OutboxEntry>,
+    /// The outbox as it was loaded from a ref, used to detect and merge concurrent updates.
+    #[serde(skip)]
+    base: Option<OutboxBase>,
+}
+
+/// Commit of the outbox ref and its entries when the outbox was loaded or last saved.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+struct OutboxBase {
+    /// None if the ref did not exist.
+    commit: Option<String>,
+    entries: Vec<OutboxEntry>,
+}
+
+impl Outbox {
+    /// Loads the outbox, which is empty if it was never saved.
+    pub fn load(location: &OutboxLocation) -> anyhow::Result<Self> {
+        match location {
+            OutboxLocation::File(path) => match std::fs::read_to_string(path) {
+                Ok(contents) => Ok(serde_json::from_str(&contents)?),
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Self::default()),
+                Err(e) => Err(e.into()),
+            },
+            OutboxLocation::GitRef(git_ref) => {
+                // The remote ref is the source of truth, as the local one only exists in the
+                // checkout that last saved the outbox. The local ref is used if the remote one
+                // cannot be fetched.
+                let commit = fetch_outbox_ref(git_ref).ok().flatten();
+                let mut outbox = read_outbox_ref(git_ref)?;
+                let entries = match commit {
+                    Some(_) => outbox.entries.clone(),
+                    None => vec![],
+                };
+                outbox.base = Some(OutboxBase { commit, entries });
+                Ok(outbox)
+            }
+        }
+    }
+
+    /// Saves the outbox, replacing the previously saved one.
+    ///
+    /// An outbox kept in a ref is pushed with a lease on the commit it was loaded from. If the
+    /// remote ref was updated concurrently, the entries added by the other update are merged into
+    /// this outbox and the push is retried.
+    pub fn save(&mut self, location: &OutboxLocation) -> anyhow::Result<()> {
+        match location {
+            OutboxLocation::File(path) if self.is_empty() => {
+                // Do not leave an empty outbox behind in the checkout.
+                if path.exists() {
+                    std::fs::remove_file(path)?;
+                }
+            }
+            OutboxLocation::File(path) => {
+                if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+                    std::fs::create_dir_all(parent)?;
+                }
+                std::fs::write(path, serde_json::to_string_pretty(self)?)?;
+            }
+            OutboxLocation::GitRef(git_ref) => {
+                for _ in 0..OUTBOX_PUSH_ATTEMPTS {
+                    let base = self.base.take().unwrap_or_default();
+                    let commit = self.commit_to_ref(git_ref)?;
+                    let lease = format!(
+                        "--force-with-lease={git_ref}:{}",
+                        base.commit.as_deref().unwrap_or_default()
+                    );
+                    let pushed = git(&["push", "--quiet", &lease, "origin", git_ref]);
+                    let Err(e) = pushed else {
+                        self.base = Some(OutboxBase {
+                            commit: Some(commit),
+                            entries: self.entries.clone(),
+                        });
+                        return Ok(());
+                    };
+                    match fetch_outbox_ref(git_ref) {
+                        Ok(remote_commit) if remote_commit != base.commit => {
+                            // The ref was updated since it was loaded, merge and retry.
+                            let remote = match remote_commit {
+                                Some(_) => read_outbox_ref(git_ref)?,
+                                None => Outbox::default(),
+                            };
+                            let remote_entries = remote.entries.clone();
+                            self.merge(&base.entries, remote);
+                            self.base = Some(OutboxBase {
+                                commit: remote_commit,
+                                entries: remote_entries,
+                            });
+                        }
+                        _ => {
+                            self.commit_to_ref(git_ref)?;
+                            println!("WARNING: outbox is only saved in the local {git_ref}: {e}");
+                            self.base = Some(base);
+                            return Ok(());
+                        }
+                    }
+                }
+                // Keep the merged entries locally, so that they are not lost.
+                self.commit_to_ref(git_ref)?;
+                println!(
+                    "WARNING: outbox is only saved in the local {git_ref}: the remote ref kept \
+                     being updated concurrently"
+                );
+            }
+        }
+        Ok(())
+    }
+
+    /// Commits the outbox to the local ref, and returns the commit.
+    fn commit_to_ref(&self, git_ref: &str) -> anyhow::Result<String> {
+        let contents = serde_json::to_string_pretty(self)?;
+        let blob = git_with_input(&["hash-object", "-w", "--stdin"], &contents)?;
+        let tree = git_with_input(
+            &["mktree"],
+            &format!("100644 blob {blob}\t{OUTBOX_REF_FILE_NAME}\n"),
+        )?;
+        let commit = git(&["commit-tree", &tree, "-m", "Update releasy outbox"])?;
+        git(&["update-ref", git_ref, &commit])?;
+        Ok(commit)
+    }
+
+    /// Merges the entries added to the remote outbox since `base` was loaded. Entries of `base`
+    /// which are no longer in this outbox were delivered and are not added back.
+    fn merge(&mut self, base: &[OutboxEntry], remote: Outbox) {
+        for entry in remote.entries {
+            if !base.iter().any(|e| e.is_same_delivery(&entry)) {
+                self.push(entry);
+            }
+        }
+    }
+
+    /// Returns an iterator over the events waiting to be delivered.
+    pub fn entries(&self) -> impl Iterator<Item = &OutboxEntry> {
+        self.entries.iter()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Adds an event waiting to be delivered, unless the same event is already waiting for the
+    /// same target.
+    pub fn push(&mut self, entry: OutboxEntry) {
+        if !self.entries.iter().any(|e| e.is_same_delivery(&entry)) {
+            self.entries.push(entry);
+        }
+    }
+
+    /// Removes the targets the event is delivered to and records the failed attempts, as reported
+    /// by emitting the event.
+    pub fn record(&mut self, event: &Event, report: &EmitReport) {
+        for outcome in report.targets() {
+            let entry = self
+                .entries
+                .iter()
+                .position(|e| &e.event == event && &e.target == outcome.repo());
+            match (entry, outcome.status()) {
+                (Some(index), TargetStatus::Sent) => {
+                    self.entries.remove(index);
+                }
+                (Some(index), TargetStatus::Failed(reason)) => {
+                    let entry = &mut self.entries[index];
+                    entry.attempts += 1;
+                    entry.last_error = Some(reason.clone());
+                }
+                _ => {}
+            }
+        }
+    }
+}
+
+/// Emits the event, keeping the targets in the outbox until they are delivered.
+///
+/// Targets are added to the outbox before anything is sent, so that they can be replayed even if
+/// emitting is interrupted.
+pub async fn emit_with_outbox(
+    event: &Event,
+    targets: Vec<Target>,
+    concurrency: usize,
+    location: &OutboxLocation,
+) -> anyhow::Result<EmitReport> {
+    let mut outbox = Outbox::load(location)?;
+    let pending = targets
+        .iter()
+        .filter(|target| target.skip_reason().is_none())
+        .map(|target| {
+            OutboxEntry::new(
+                event.clone(),
+                target.repo().clone(),
+                target.delivery().clone(),
+            )
+        })
+        .collect::<Vec<_>>();
+    if pending.is_empty() {
+        return emit(event, targets, concurrency).await;
+    }
+    for entry in pending {
+        outbox.push(entry);
+    }
+    outbox.save(location)?;
+
+    let report = emit(event, targets, concurrency).await?;
+    outbox.record(event, &report);
+    outbox.save(location)?;
+    Ok(report)
+}
+
+/// Redelivers every event waiting in the outbox and saves the outbox with the events that still
+/// could not be delivered.
+///
+/// Events delivered again by an interrupted emit are only received twice by their targets, which
+/// handle events idempotently.
+pub async fn replay(location: &OutboxLocation, concurrency: usize) -> anyhow::Result<EmitReport> {
+    let mut outbox = Outbox::load(location)?;
+    let mut events: Vec<(Event, Vec<Target>)> = vec![];
+    for entry in outbox.entries() {
+        let target = Target::new(entry.target.clone()).with_delivery(entry.delivery.clone());
+        match events.iter_mut().find(|(event, _)| event == &entry.event) {
+            Some((_, targets)) => targets.push(target),
+            None => events.push((entry.event.clone(), vec![target])),
+        }
+    }
+
+    let mut outcomes = vec![];
+    for (event, targets) in events {
+        let report = emit(&event, targets, concurrency).await?;
+        outbox.record(&event, &report);
+        outcomes.extend(report.targets().cloned());
+    }
+    outbox.save(location)?;
+    Ok(EmitReport::new(outcomes))
+}
+
+/// Fetches the outbox ref from `origin` into the local ref, and returns its remote commit which
+/// is None if the remote ref does not exist.
+fn fetch_outbox_ref(git_ref: &str) -> anyhow::Result<Option<String>> {
+    let listed = git(&["ls-remote", "origin", git_ref])?;
+    let commit = listed.split_whitespace().next().map(str::to_string);
+    if commit.is_some() {
+        git(&[
+            "fetch",
+            "--quiet",
+            "origin",
+            &format!("+{git_ref}:{git_ref}"),
+        ])?;
+    }
+    Ok(commit)
+}
+
+/// Reads the outbox held by the local ref, which is empty if the ref does not exist.
+fn read_outbox_ref(git_ref: &str) -> anyhow::Result<Outbox> {
+    match git(&["show", &format!("{git_ref}:{OUTBOX_REF_FILE_NAME}")]) {
+        Ok(contents) => Ok(serde_json::from_str(&contents)?),
+        Err(_) => Ok(Outbox::default()),
+    }
+}
+
+fn git(args: &[&str]) -> anyhow::Result<String> {
+    git_with_input(args, "")
+}
+
+/// Runs git with the given stdin, returning the trimmed stdout.
+fn git_with_input(args: &[&str], input: &str) -> anyhow::Result<String> {
+    let mut child = Command::new("git")
+        .args(args)
+        .env("GIT_AUTHOR_NAME", "releasy")
+        .env("GIT_AUTHOR_EMAIL", "releasy@fuel.sh")
+        .env("GIT_COMMITTER_NAME", "releasy")
+        .env("GIT_COMMITTER_EMAIL", "releasy@fuel.sh")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()?;
+    child
+        .stdin
+        .take()
+        .expect("stdin is piped")
+        .write_all(input.as_bytes())?;
+    let output = child.wait_with_output()?;
+    if !output.status.success() {
+        anyhow::bail!(
+            "git {} failed: {}",
+            args.join(" "),
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Outbox, OutboxEntry, OutboxLocation};
+    use crate::report::{EmitReport, TargetOutcome, TargetStatus};
+    use releasy_core::{
+        delivery::Delivery,
+        event::{ClientPayload, Event, EventDetails, EventType},
+        repo::Repo,
+    };
+    use std::path::PathBuf;
+
+    fn repo(name: &str) -> Repo {
+        Repo::new(name.to_string(), "FuelLabs".to_string())
+    }
+
+    #[test]
+    fn parse_outbox_location() {
+        assert_eq!(
+            "git:refs/releasy/outbox".parse::<OutboxLocation>().unwrap(),
+            OutboxLocation::GitRef("refs/releasy/outbox".to_string())
+        );
+        assert_eq!(
+            "outbox.json".parse::<OutboxLocation>().unwrap(),
+            OutboxLocation::File(PathBuf::from("outbox.json"))
+        );
+        assert!("git:outbox".parse::<OutboxLocation>().is_err());
+    }
+
+    #[test]
+    fn record_outcomes_of_pending_events() {
+        let event = Event::new(
+            EventType::NewCommitToDependency,
+            ClientPayload::new(
+                repo("fuel-core"),
+                EventDetails::new(Some("337d0eaa".to_string()), None),
+            ),
+        );
+        let mut outbox = Outbox::default();
+        for target in ["sway", "fuels-rs", "sway"] {
+            outbox.push(OutboxEntry::new(
+                event.clone(),
+                repo(target),
+                Delivery::default(),
+            ));
+        }
+        assert_eq!(outbox.entries().count(), 2);
+
+        let report = EmitReport::new(vec![
+            TargetOutcome::new(repo("sway"), TargetStatus::Sent),
+            TargetOutcome::new(
+                repo("fuels-rs"),
+                TargetStatus::Failed("timeout".to_string()),
+            ),
+        ]);
+        outbox.record(&event, &report);
+
+        let entries = outbox.entries().collect::<Vec<_>>();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].target(), &repo("fuels-rs"));
+        assert_eq!(entries[0].attempts(), 1);
+        assert_eq!(entries[0].last_error(), Some("timeout"));
+    }
+
+    #[test]
+    fn merge_concurrently_added_entries() {
+        let event = Event::new(
+            EventType::NewRelease,
+            ClientPayload::new(
+                repo("sway"),
+                EventDetails::new(None, Some("v0.46.0".to_string())),
+            ),
+        );
+        let entry =
+            |target: &str| OutboxEntry::new(event.clone(), repo(target), Delivery::default());
+        let base = vec![entry("fuels-rs"), entry("fuel-core")];
+        // fuels-rs was delivered and fuels-ts was added by this run.
+        let mut outbox = Outbox::default();
+        outbox.push(entry("fuel-core"));
+        outbox.push(entry("fuels-ts"));
+        // forc-wallet was added concurrently.
+        let mut remote = Outbox::default();
+        for target in ["fuels-rs", "fuel-core", "forc-wallet"] {
+            remote.push(entry(target));
+        }
+
+        outbox.merge(&base, remote);
+        let targets = outbox
+            .entries()
+            .map(|e| e.target().name().to_string())
+            .collect::<Vec<_>>();
+        assert_eq!(targets, vec!["fuel-core", "fuels-ts", "forc-wallet"]);
+    }
+
+    #[test]
+    fn save_and_load_outbox_file() {
+        let path = std::env::temp_dir()
+            .join(format!("releasy-outbox-test-{}", std::process::id()))
+            .join("outbox.json");
+        let location = OutboxLocation::File(path.clone());
+        assert!(Outbox::load(&location).unwrap().is_empty());
+
+        let mut outbox = Outbox::default();
+        outbox.push(OutboxEntry::new(
+            Event::new(
+                EventType::NewRelease,
+                ClientPayload::new(
+                    repo("sway"),
+                    EventDetails::new(None, Some("v0.46.0".to_string())),
+                ),
+            ),
+            repo("fuels-rs"),
+            Delivery::default(),
+        ));
+        outbox.save(&location).unwrap();
+        assert_eq!(Outbox::load(&location).unwrap(), outbox);
+
+        Outbox::default().save(&location).unwrap();
+        assert!(!path.exists());
+        std::fs::remove_dir_all(path.parent().unwrap()).unwrap();
+    }
+}
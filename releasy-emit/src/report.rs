@@ -0,0 +1,182 @@
+use std::fmt::Display;
+
+use releasy_core::repo::Repo;
+use serde::Serialize;
+
+/// Outcome of emitting an event to a single target repo.
+#[derive(Serialize, Clone, Debug, PartialEq, Eq)]
+#[serde(tag = "status", content = "reason", rename_all = "kebab-case")]
+pub enum TargetStatus {
+    Sent,
+    /// The target was not notified, as the edge connecting it does not want the event.
+    Skipped(String),
+    Failed(String),
+}
+
+/// A target repo and the outcome of emitting the event to it.
+#[derive(Serialize, Clone, Debug, PartialEq, Eq)]
+pub struct TargetOutcome {
+    repo: Repo,
+    #[serde(flatten)]
+    status: TargetStatus,
+}
+
+impl TargetOutcome {
+    pub fn new(repo: Repo, status: TargetStatus) -> Self {
+        Self { repo, status }
+    }
+
+    pub fn repo(&self) -> &Repo {
+        &self.repo
+    }
+
+    pub fn status(&self) -> &TargetStatus {
+        &self.status
+    }
+}
+
+/// Outcomes of emitting an event to every target repo, in the order of the targets.
+#[derive(Serialize, Clone, Debug, Default, PartialEq, Eq)]
+pub struct EmitReport {
+    targets: Vec<TargetOutcome>,
+}
+
+impl EmitReport {
+    /// Exit code used when every target is either notified or skipped.
+    pub const EXIT_CODE_SUCCESS: u8 = 0;
+    /// Exit code used when no target could be notified.
+    pub const EXIT_CODE_TOTAL_FAILURE: u8 = 1;
+    /// Exit code used when some of the targets could be notified.
+    pub const EXIT_CODE_PARTIAL_FAILURE: u8 = 2;
+
+    pub fn new(targets: Vec<TargetOutcome>) -> Self {
+        Self { targets }
+    }
+
+    /// Returns an iterator over the outcome of each target.
+    pub fn targets(&self) -> impl Iterator<Item = &TargetOutcome> {
+        self.targets.iter()
+    }
+
+    fn count(&self, predicate: impl Fn(&TargetStatus) -> bool) -> usize {
+        self.targets
+            .iter()
+            .filter(|target| predicate(&target.status))
+            .count()
+    }
+
+    /// Returns the number of targets the event is sent to.
+    pub fn sent(&self) -> usize {
+        self.count(|status| matches!(status, TargetStatus::Sent))
+    }
+
+    /// Returns the number of targets skipped.
+    pub fn skipped(&self) -> usize {
+        self.count(|status| matches!(status, TargetStatus::Skipped(_)))
+    }
+
+    /// Returns the number of targets the event could not be sent to.
+    pub fn failed(&self) -> usize {
+        self.count(|status| matches!(status, TargetStatus::Failed(_)))
+    }
+
+    /// Returns the exit code summarizing this report, distinguishing partial from total failure.
+    pub fn exit_code(&self) -> u8 {
+        match (self.sent(), self.failed()) {
+            (_, 0) => Self::EXIT_CODE_SUCCESS,
+            (0, _) => Self::EXIT_CODE_TOTAL_FAILURE,
+            _ => Self::EXIT_CODE_PARTIAL_FAILURE,
+        }
+    }
+}
+
+impl Display for EmitReport {
+    /// Formats the report as a table.
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let rows = self
+            .targets
+            .iter()
+            .map(|target| {
+                let repo = format!("{}/{}", target.repo.owner(), target.repo.name());
+                let (status, reason) = match &target.status {
+                    TargetStatus::Sent => ("sent", ""),
+                    TargetStatus::Skipped(reason) => ("skipped", reason.as_str()),
+                    TargetStatus::Failed(reason) => ("failed", reason.as_str()),
+                };
+                (repo, status, reason)
+            })
+            .collect::<Vec<_>>();
+        let repo_width = rows
+            .iter()
+            .map(|(repo, _, _)| repo.len())
+            .chain(std::iter::once("REPO".len()))
+            .max()
+            .unwrap_or_default();
+
+        writeln!(f, "{:repo_width$}  {:7}  DETAILS", "REPO", "STATUS")?;
+        for (repo, status, reason) in rows {
+            writeln!(f, "{repo:repo_width$}  {status:7}  {reason}")?;
+        }
+        write!(
+            f,
+            "{} sent, {} skipped, {} failed",
+            self.sent(),
+            self.skipped(),
+            self.failed()
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{EmitReport, TargetOutcome, TargetStatus};
+    use releasy_core::repo::Repo;
+
+    fn outcome(name: &str, status: TargetStatus) -> TargetOutcome {
+        TargetOutcome::new(Repo::new(name.to_string(), "FuelLabs".to_string()), status)
+    }
+
+    #[test]
+    fn exit_code_distinguishes_partial_and_total_failure() {
+        let skipped = TargetStatus::Skipped("not subscribed".to_string());
+        let failed = TargetStatus::Failed("404 Not Found".to_string());
+
+        let report = EmitReport::new(vec![
+            outcome("sway", TargetStatus::Sent),
+            outcome("forc-wallet", skipped.clone()),
+        ]);
+        assert_eq!(report.exit_code(), EmitReport::EXIT_CODE_SUCCESS);
+
+        let report = EmitReport::new(vec![
+            outcome("sway", TargetStatus::Sent),
+            outcome("forc-wallet", failed.clone()),
+        ]);
+        assert_eq!(report.exit_code(), EmitReport::EXIT_CODE_PARTIAL_FAILURE);
+
+        let report = EmitReport::new(vec![
+            outcome("sway", failed),
+            outcome("forc-wallet", skipped),
+        ]);
+        assert_eq!(report.exit_code(), EmitReport::EXIT_CODE_TOTAL_FAILURE);
+    }
+
+    #[test]
+    fn serialize_report_to_json() {
+        let report = EmitReport::new(vec![
+            outcome("sway", TargetStatus::Sent),
+            outcome("forc-wallet", TargetStatus::Failed("timeout".to_string())),
+        ]);
+        let json = serde_json::to_value(&report).unwrap();
+        let expected = serde_json::json!({
+            "targets": [
+                { "repo": { "name": "sway", "owner": "FuelLabs" }, "status": "sent" },
+                {
+                    "repo": { "name": "forc-wallet", "owner": "FuelLabs" },
+                    "status": "failed",
+                    "reason": "timeout"
+                }
+            ]
+        });
+        assert_eq!(json, expected);
+    }
+}
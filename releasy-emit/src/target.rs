@@ -0,0 +1,281 @@
+//! Resolution of the repos an event is emitted to.
+use std::{collections::HashMap, str::FromStr};
+
+use releasy_core::{
+    delivery::Delivery,
+    event::{Event, EventDetails},
+    repo::Repo,
+};
+use releasy_graph::{manifest::Manifest, plan::Plan};
+use serde::Serialize;
+
+/// Selects repos by name, owner or group.
+///
+/// Parsed from one of the following forms:
+///
+/// ```text
+/// <name>           repos with the given name, regardless of their owner
+/// <owner>/<name>   the repo with the given owner and name
+/// <owner>/*        repos with the given owner
+/// group:<group>    repos with the given group in their settings
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum RepoSelector {
+    Repo { owner: Option<String>, name: String },
+    Owner(String),
+    Group(String),
+}
+
+impl RepoSelector {
+    /// Returns true if the repo, belonging to the given groups, is selected.
+    pub fn matches<'a>(&self, repo: &Repo, mut groups: impl Iterator<Item = &'a String>) -> bool {
+        match self {
+            RepoSelector::Repo { owner, name } => {
+                repo.name() == name && owner.as_deref().is_none_or(|o| repo.owner() == o)
+            }
+            RepoSelector::Owner(owner) => repo.owner() == owner,
+            RepoSelector::Group(group) => groups.any(|g| g == group),
+        }
+    }
+}
+
+impl FromStr for RepoSelector {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let selector = if let Some(group) = s.strip_prefix("group:") {
+            RepoSelector::Group(group.to_string())
+        } else if let Some((owner, name)) = s.split_once('/') {
+            if name == "*" {
+                RepoSelector::Owner(owner.to_string())
+            } else {
+                RepoSelector::Repo {
+                    owner: Some(owner.to_string()),
+                    name: name.to_string(),
+                }
+            }
+        } else {
+            RepoSelector::Repo {
+                owner: None,
+                name: s.to_string(),
+            }
+        };
+        match &selector {
+            RepoSelector::Repo { owner, name }
+                if name.is_empty() || owner.as_deref() == Some("") =>
+            {
+                anyhow::bail!("invalid repo selector `{s}`")
+            }
+            RepoSelector::Owner(value) | RepoSelector::Group(value) if value.is_empty() => {
+                anyhow::bail!("invalid repo selector `{s}`")
+            }
+            _ => Ok(selector),
+        }
+    }
+}
+
+/// Describes which downstream repos an event is emitted to.
+///
+/// By default every immediate downstream repo is targeted.
+#[derive(Clone, Debug, Default)]
+pub struct TargetSelection {
+    /// If not empty, only repos matching one of these selectors are targeted.
+    only: Vec<RepoSelector>,
+    /// Repos matching one of these selectors are not targeted.
+    exclude: Vec<RepoSelector>,
+    /// Targets the whole downstream closure instead of the immediate downstream repos.
+    transitive: bool,
+    /// Groups of each repo, used for matching group selectors.
+    groups: HashMap<Repo, Vec<String>>,
+}
+
+impl TargetSelection {
+    pub fn new(only: Vec<RepoSelector>, exclude: Vec<RepoSelector>, transitive: bool) -> Self {
+        Self {
+            only,
+            exclude,
+            transitive,
+            groups: HashMap::new(),
+        }
+    }
+
+    /// Reads the groups of each repo from the settings declared in the manifest.
+    pub fn with_groups(mut self, manifest: &Manifest) -> Self {
+        self.groups = manifest
+            .repos()
+            .map(|repo| {
+                let groups = manifest.settings(repo).groups().cloned().collect();
+                (repo.clone(), groups)
+            })
+            .collect();
+        self
+    }
+
+    pub fn is_transitive(&self) -> bool {
+        self.transitive
+    }
+
+    /// Returns true if the repo is selected by the `only` and `exclude` selectors.
+    pub fn is_selected(&self, repo: &Repo) -> bool {
+        let matches = |selector: &RepoSelector| {
+            selector.matches(repo, self.groups.get(repo).into_iter().flatten())
+        };
+        (self.only.is_empty() || self.only.iter().any(matches)) && !self.exclude.iter().any(matches)
+    }
+}
+
+/// A downstream repo considered for an event.
+#[derive(Serialize, Clone, Debug, PartialEq, Eq)]
+pub struct Target {
+    repo: Repo,
+    delivery: Delivery,
+    /// Reason for not sending the event to this repo, if it is skipped.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    skip_reason: Option<String>,
+}
+
+impl Target {
+    /// Creates a target receiving events with the default delivery.
+    pub fn new(repo: Repo) -> Self {
+        Self {
+            repo,
+            delivery: Delivery::default(),
+            skip_reason: None,
+        }
+    }
+
+    pub fn repo(&self) -> &Repo {
+        &self.repo
+    }
+
+    pub fn skip_reason(&self) -> Option<&str> {
+        self.skip_reason.as_deref()
+    }
+
+    pub fn delivery(&self) -> &Delivery {
+        &self.delivery
+    }
+
+    /// Sets how the event is delivered to this repo.
+    pub fn with_delivery(mut self, delivery: Delivery) -> Self {
+        self.delivery = delivery;
+        self
+    }
+}
+
+/// Returns the downstream repos of `current_repo` considered for the event, in the order they are
+/// reached in the plan.
+///
+/// Repos are skipped if the event commit is marked with `[skip releasy]`, if they are not
+/// selected, or if the edge connecting them to `current_repo` does not subscribe to the event
+/// type, does not track the branch of the event or does not watch any of the changed paths of the
+/// event. In transitive mode, repos further downstream are reached through
+/// edges subscribing to the event type, while branch and path filters only apply to the edges of
+/// `current_repo` as they describe its branches and paths.
+pub fn resolve_targets(
+    event: &Event,
+    plan: &Plan,
+    current_repo: &Repo,
+    selection: &TargetSelection,
+) -> anyhow::Result<Vec<Target>> {
+    let details = event.client_payload().details();
+    let changed_paths = details.changed_paths();
+    let candidates: Vec<_> = if selection.is_transitive() {
+        plan.downstream_closure(current_repo.clone(), |edge| {
+            edge.subscribes_to(event.event_type())
+        })?
+    } else {
+        plan.downstream_edges(current_repo.clone())?.collect()
+    };
+
+    let targets = candidates
+        .into_iter()
+        .map(|(repo, edge)| {
+            let direct_edge = plan.edge(current_repo, repo);
+            let skip_reason = if details.is_skipped() {
+                Some(format!(
+                    "commit is marked with `{}`",
+                    EventDetails::SKIP_MARKER
+                ))
+            } else if !selection.is_selected(repo) {
+                Some("not selected".to_string())
+            } else if !edge.subscribes_to(event.event_type()) {
+                Some(format!("not subscribed to `{}` events", event.event_type()))
+            } else if direct_edge.is_some_and(|edge| {
+                edge.tracking_branch(
+                    current_repo,
+                    plan.default_branch(current_repo),
+                    details.branch().map(String::as_str),
+                )
+                .is_none()
+            }) {
+                Some(format!("does not track {:?}", details.branch()))
+            } else if direct_edge
+                .zip(changed_paths)
+                .is_some_and(|(edge, changed_paths)| {
+                    !edge.touches_paths(changed_paths.iter().map(String::as_str))
+                })
+            {
+                Some("none of the watched paths are changed".to_string())
+            } else {
+                None
+            };
+            Target {
+                repo: repo.clone(),
+                delivery: Delivery::default(),
+                skip_reason,
+            }
+        })
+        .collect();
+    Ok(targets)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{RepoSelector, TargetSelection};
+    use releasy_core::repo::Repo;
+
+    #[test]
+    fn parse_repo_selectors() {
+        assert_eq!(
+            "sway".parse::<RepoSelector>().unwrap(),
+            RepoSelector::Repo {
+                owner: None,
+                name: "sway".to_string()
+            }
+        );
+        assert_eq!(
+            "FuelLabs/sway".parse::<RepoSelector>().unwrap(),
+            RepoSelector::Repo {
+                owner: Some("FuelLabs".to_string()),
+                name: "sway".to_string()
+            }
+        );
+        assert_eq!(
+            "FuelLabs/*".parse::<RepoSelector>().unwrap(),
+            RepoSelector::Owner("FuelLabs".to_string())
+        );
+        assert_eq!(
+            "group:sdks".parse::<RepoSelector>().unwrap(),
+            RepoSelector::Group("sdks".to_string())
+        );
+        assert!("group:".parse::<RepoSelector>().is_err());
+        assert!("/sway".parse::<RepoSelector>().is_err());
+    }
+
+    #[test]
+    fn exclude_takes_precedence_over_only() {
+        let sway = Repo::new("sway".to_string(), "FuelLabs".to_string());
+        let wallet = Repo::new("forc-wallet".to_string(), "FuelLabs".to_string());
+        let other = Repo::new("sway".to_string(), "Other".to_string());
+
+        let selection = TargetSelection::new(
+            vec!["FuelLabs/*".parse().unwrap()],
+            vec!["forc-wallet".parse().unwrap()],
+            false,
+        );
+        assert!(selection.is_selected(&sway));
+        assert!(!selection.is_selected(&wallet));
+        assert!(!selection.is_selected(&other));
+    }
+}
@@ -0,0 +1,232 @@
+//! Consistency checks between the copies of a plan carried by different repos.
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fmt::Display,
+};
+
+use releasy_core::repo::Repo;
+
+use crate::{
+    error::BuildPlanError,
+    manifest::{DependencyDetails, Manifest},
+};
+
+/// A disagreement between copies of a plan.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Inconsistency {
+    /// The repo is declared by some of the copies only.
+    Repo {
+        repo: Repo,
+        missing_from: Vec<String>,
+    },
+    /// Copies declare different details for the edge between two repos.
+    ///
+    /// Each variant lists the labels of the copies declaring the same details, where `None` means
+    /// the edge is missing from those copies.
+    Edge {
+        upstream: Repo,
+        downstream: Repo,
+        variants: Vec<(Option<DependencyDetails>, Vec<String>)>,
+    },
+}
+
+impl Display for Inconsistency {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Inconsistency::Repo { repo, missing_from } => {
+                writeln!(f, "repo {}/{}:", repo.owner(), repo.name())?;
+                writeln!(f, "  missing from: {}", missing_from.join(", "))
+            }
+            Inconsistency::Edge {
+                upstream,
+                downstream,
+                variants,
+            } => {
+                writeln!(
+                    f,
+                    "edge {}/{} -> {}/{}:",
+                    upstream.owner(),
+                    upstream.name(),
+                    downstream.owner(),
+                    downstream.name()
+                )?;
+                for (details, labels) in variants {
+                    match details {
+                        Some(details) => {
+                            writeln!(f, "  declared as {details} in: {}", labels.join(", "))?
+                        }
+                        None => writeln!(f, "  missing from: {}", labels.join(", "))?,
+                    }
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+/// Compares the graphs described by the given copies of a plan, each labeled with a name used for
+/// reporting (e.g. the repo carrying the copy).
+///
+/// Repos are compared by their details, so copies are free to use different keys for the same
+/// repo. `current-repo` and repo settings are not compared.
+pub fn check_consistency(
+    copies: &[(String, Manifest)],
+) -> Result<Vec<Inconsistency>, BuildPlanError> {
+    let mut repos: BTreeMap<Repo, BTreeSet<&str>> = BTreeMap::new();
+    let mut edges: BTreeMap<(Repo, Repo), BTreeMap<&str, DependencyDetails>> = BTreeMap::new();
+    for (label, manifest) in copies {
+        for (upstream, downstream, details) in normalized_edges(manifest)? {
+            edges
+                .entry((upstream, downstream))
+                .or_default()
+                .insert(label, details);
+        }
+        for entry in manifest.repo.values() {
+            repos
+                .entry(entry.details().clone())
+                .or_default()
+                .insert(label);
+        }
+    }
+
+    let mut inconsistencies = vec![];
+    for (repo, declared_in) in repos {
+        let missing_from = copies
+            .iter()
+            .map(|(label, _)| label)
+            .filter(|label| !declared_in.contains(label.as_str()))
+            .cloned()
+            .collect::<Vec<_>>();
+        if !missing_from.is_empty() {
+            inconsistencies.push(Inconsistency::Repo { repo, missing_from });
+        }
+    }
+    for ((upstream, downstream), declared_in) in edges {
+        let mut variants: Vec<(Option<DependencyDetails>, Vec<String>)> = vec![];
+        for (label, _) in copies {
+            let details = declared_in.get(label.as_str()).cloned();
+            match variants.iter_mut().find(|(variant, _)| variant == &details) {
+                Some((_, labels)) => labels.push(label.clone()),
+                None => variants.push((details, vec![label.clone()])),
+            }
+        }
+        if variants.len() > 1 {
+            inconsistencies.push(Inconsistency::Edge {
+                upstream,
+                downstream,
+                variants,
+            });
+        }
+    }
+    Ok(inconsistencies)
+}
+
+/// Returns the edges of the manifest, with the keys of the upstream repos erased from the details
+/// so that they can be compared across copies.
+fn normalized_edges(
+    manifest: &Manifest,
+) -> Result<Vec<(Repo, Repo, DependencyDetails)>, BuildPlanError> {
+    let mut edges = vec![];
+    for entry in manifest.repo.values() {
+        let downstream = entry.details();
+        for dependency in entry.dependencies() {
+            let upstream = manifest
+                .repo
+                .get(dependency.name())
+                .ok_or_else(|| {
+                    BuildPlanError::MissingProjectDefinition(
+                        downstream.name().to_string(),
+                        dependency.name().to_string(),
+                    )
+                })?
+                .details();
+            let details = dependency.details().with_name(String::new());
+            edges.push((upstream.clone(), downstream.clone(), details));
+        }
+    }
+    Ok(edges)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{check_consistency, Inconsistency};
+    use crate::manifest::ManifestFile;
+
+    fn manifest(current_repo: &str, sway_dependencies: &str) -> crate::manifest::Manifest {
+        let manifest_str = format!(
+            r#"
+[current-repo]
+name = "{current_repo}"
+owner = "FuelLabs"
+
+[repo.fuel-core.details]
+name = "fuel-core"
+owner = "FuelLabs"
+
+[repo.sdk.details]
+name = "fuels-rs"
+owner = "FuelLabs"
+
+[repo.sdk]
+dependencies = ["fuel-core"]
+
+[repo.sway.details]
+name = "sway"
+owner = "FuelLabs"
+
+[repo.sway]
+dependencies = {sway_dependencies}
+"#
+        );
+        ManifestFile::try_from(manifest_str).unwrap().manifest()
+    }
+
+    #[test]
+    fn consistent_copies_with_different_current_repos() {
+        let copies = vec![
+            ("fuels-rs".to_string(), manifest("fuels-rs", r#"["sdk"]"#)),
+            ("sway".to_string(), manifest("sway", r#"["sdk"]"#)),
+        ];
+        assert!(check_consistency(&copies).unwrap().is_empty());
+    }
+
+    #[test]
+    fn flag_copies_disagreeing_on_edges() {
+        let copies = vec![
+            ("fuel-core".to_string(), manifest("fuel-core", r#"["sdk"]"#)),
+            (
+                "fuels-rs".to_string(),
+                manifest("fuels-rs", r#"["sdk", "fuel-core"]"#),
+            ),
+            (
+                "sway".to_string(),
+                manifest("sway", r#"[{ name = "sdk", events = ["new-release"] }]"#),
+            ),
+        ];
+        let inconsistencies = check_consistency(&copies).unwrap();
+        assert_eq!(inconsistencies.len(), 2);
+
+        // `fuel-core -> sway` is only declared by the copy in `fuels-rs`.
+        let Inconsistency::Edge {
+            upstream, variants, ..
+        } = &inconsistencies[0]
+        else {
+            panic!("expected an edge inconsistency");
+        };
+        assert_eq!(upstream.name(), "fuel-core");
+        assert_eq!(variants[0].0, None);
+        assert_eq!(variants[0].1, vec!["fuel-core", "sway"]);
+        assert_eq!(variants[1].1, vec!["fuels-rs"]);
+
+        // `fuels-rs -> sway` is declared by every copy, but `sway` restricts its events.
+        let Inconsistency::Edge {
+            upstream, variants, ..
+        } = &inconsistencies[1]
+        else {
+            panic!("expected an edge inconsistency");
+        };
+        assert_eq!(upstream.name(), "fuels-rs");
+        assert_eq!(variants[0].1, vec!["fuel-core", "fuels-rs"]);
+        assert_eq!(variants[1].1, vec!["sway"]);
+    }
+}
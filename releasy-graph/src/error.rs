@@ -8,6 +8,10 @@ pub enum BuildPlanError {
     MissingProjectDefinition(String, String),
     #[error("provided repo `{0}` not found in dependency graph")]
     RepoNotFoundInGraph(Repo),
+    #[error("`{0}` declares an invalid path pattern (`{1}`) for a dependency: {2}")]
+    InvalidPathPattern(String, String, glob::PatternError),
+    #[error("`{0}` declares a base branch for `{2}` of `{1}`, which is not a tracked branch")]
+    UntrackedBaseBranch(String, String, String),
 }
 
 #[derive(Error, Debug)]
@@ -16,4 +20,20 @@ pub enum ManifestFileError {
     MissingManifestFile(String, std::io::Error),
     #[error("failed to parse manifest: {0}")]
     FailedToParseManifest(toml::de::Error),
+    #[error("invalid `extends` table: {0}")]
+    InvalidPlanSource(String),
+    #[error("failed to fetch plan `{0}`: {1}")]
+    FailedToFetchPlan(String, String),
+    #[error("plans cannot extend each other deeper than {0} levels, is there a cycle?")]
+    ExtendsTooDeep(usize),
+    #[error("custom event `{0}` should only contain lowercase letters, digits and dashes")]
+    InvalidCustomEventName(String),
+}
+
+#[derive(Error, Debug)]
+pub enum InferError {
+    #[error("failed to read checkout at `{0}`: {1}")]
+    FailedToReadCheckout(String, std::io::Error),
+    #[error("failed to parse package manifest at `{0}`: {1}")]
+    FailedToParsePackageManifest(String, toml::de::Error),
 }
@@ -0,0 +1,463 @@
+//! Infers dependency relations between repos from the package manifests of their local checkouts.
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fmt::Display,
+    path::{Path, PathBuf},
+};
+
+use releasy_core::repo::Repo;
+use toml::{Table, Value};
+
+use crate::{error::InferError, manifest::Manifest};
+
+const CARGO_MANIFEST_FILE_NAME: &str = "Cargo.toml";
+const CARGO_LOCK_FILE_NAME: &str = "Cargo.lock";
+const FORC_MANIFEST_FILE_NAME: &str = "Forc.toml";
+
+/// Directories that never contain package manifests we are interested in.
+const IGNORED_DIRS: [&str; 3] = ["target", "node_modules", "out"];
+
+/// Cargo and forc tables listing dependencies of a package.
+const DEPENDENCY_TABLES: [&str; 4] = [
+    "dependencies",
+    "dev-dependencies",
+    "build-dependencies",
+    "contract-dependencies",
+];
+
+/// A dependency declared in a package manifest.
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub struct PackageDependency {
+    /// Name of the package depended on.
+    name: String,
+    /// Git URL of the dependency, if it is a git dependency.
+    git: Option<String>,
+}
+
+impl PackageDependency {
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn git(&self) -> Option<&str> {
+        self.git.as_deref()
+    }
+}
+
+/// Packages provided and dependencies declared by the manifests of a local checkout.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Checkout {
+    /// Names of the published packages found in the checkout.
+    packages: BTreeSet<String>,
+    /// Dependencies declared by the packages found in the checkout.
+    dependencies: BTreeSet<PackageDependency>,
+}
+
+impl Checkout {
+    /// Scans every `Cargo.toml`, `Cargo.lock` and `Forc.toml` under `path`.
+    pub fn scan(path: &Path) -> Result<Self, InferError> {
+        let mut checkout = Checkout::default();
+        let mut lock_sources = BTreeMap::new();
+        for file in manifest_files(path)? {
+            let table = read_toml(&file)?;
+            let file_name = file.file_name().and_then(|name| name.to_str());
+            match file_name {
+                Some(CARGO_LOCK_FILE_NAME) => collect_lock_sources(&table, &mut lock_sources),
+                Some(CARGO_MANIFEST_FILE_NAME) => checkout.collect_manifest(&table, "package"),
+                Some(FORC_MANIFEST_FILE_NAME) => checkout.collect_manifest(&table, "project"),
+                _ => {}
+            }
+        }
+
+        // Git sources resolved in lock files are used for dependencies declared without one, for
+        // example the ones inherited from a workspace or declared with a version only.
+        checkout.dependencies = checkout
+            .dependencies
+            .into_iter()
+            .map(|dependency| match dependency.git {
+                Some(_) => dependency,
+                None => PackageDependency {
+                    git: lock_sources.get(&dependency.name).cloned(),
+                    name: dependency.name,
+                },
+            })
+            .collect();
+
+        Ok(checkout)
+    }
+
+    /// Returns an iterator over the names of the published packages found in the checkout.
+    pub fn packages(&self) -> impl Iterator<Item = &String> {
+        self.packages.iter()
+    }
+
+    /// Returns an iterator over the dependencies declared in the checkout.
+    pub fn dependencies(&self) -> impl Iterator<Item = &PackageDependency> {
+        self.dependencies.iter()
+    }
+
+    /// Collects the package name and dependencies declared in a `Cargo.toml` or `Forc.toml`.
+    fn collect_manifest(&mut self, table: &Table, package_table: &str) {
+        if let Some(package) = table.get(package_table).and_then(Value::as_table) {
+            let published = package
+                .get("publish")
+                .and_then(Value::as_bool)
+                .unwrap_or(true);
+            if let (Some(name), true) = (package.get("name").and_then(Value::as_str), published) {
+                self.packages.insert(name.to_string());
+            }
+        }
+
+        let mut dependency_tables = vec![table];
+        if let Some(workspace) = table.get("workspace").and_then(Value::as_table) {
+            dependency_tables.push(workspace);
+        }
+        if let Some(targets) = table.get("target").and_then(Value::as_table) {
+            dependency_tables.extend(targets.values().filter_map(Value::as_table));
+        }
+        for dependency_table in dependency_tables {
+            for table_name in DEPENDENCY_TABLES {
+                if let Some(dependencies) =
+                    dependency_table.get(table_name).and_then(Value::as_table)
+                {
+                    self.collect_dependencies(dependencies);
+                }
+            }
+        }
+
+        // Patches point to the sources actually used, so they describe relations as well.
+        if let Some(patches) = table.get("patch").and_then(Value::as_table) {
+            for patch in patches.values().filter_map(Value::as_table) {
+                self.collect_dependencies(patch);
+            }
+        }
+    }
+
+    fn collect_dependencies(&mut self, dependencies: &Table) {
+        for (key, value) in dependencies {
+            let (name, git) = match value.as_table() {
+                Some(details) => (
+                    details
+                        .get("package")
+                        .and_then(Value::as_str)
+                        .unwrap_or(key),
+                    details.get("git").and_then(Value::as_str),
+                ),
+                None => (key.as_str(), None),
+            };
+            self.dependencies.insert(PackageDependency {
+                name: name.to_string(),
+                git: git.map(|git| git.to_string()),
+            });
+        }
+    }
+}
+
+/// Difference between the declared and the inferred dependencies of a repo.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DependencyDiff {
+    /// Key of the repo in the manifest.
+    key: String,
+    /// Keys of the dependencies declared in the manifest.
+    declared: BTreeSet<String>,
+    /// Keys of the inferred dependencies, with the names of the packages causing the relation.
+    ///
+    /// `None` if there is no local checkout of the repo.
+    inferred: Option<BTreeMap<String, BTreeSet<String>>>,
+}
+
+impl DependencyDiff {
+    /// Returns the key of the repo in the manifest.
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+
+    /// Returns true if there was no local checkout to infer dependencies of this repo from.
+    pub fn missing_checkout(&self) -> bool {
+        self.inferred.is_none()
+    }
+
+    /// Returns an iterator over inferred dependencies that are not declared in the manifest.
+    pub fn additions(&self) -> impl Iterator<Item = &String> {
+        self.inferred
+            .iter()
+            .flat_map(|inferred| inferred.keys())
+            .filter(|key| !self.declared.contains(*key))
+    }
+
+    /// Returns an iterator over declared dependencies that could not be inferred.
+    pub fn stale(&self) -> impl Iterator<Item = &String> {
+        self.declared.iter().filter(|key| {
+            self.inferred
+                .as_ref()
+                .is_some_and(|inferred| !inferred.contains_key(*key))
+        })
+    }
+
+    /// Returns true if the declared dependencies match the inferred ones.
+    pub fn is_in_sync(&self) -> bool {
+        self.additions().next().is_none() && self.stale().next().is_none()
+    }
+
+    /// Returns the dependencies proposed for this repo, which are the inferred ones if there is a
+    /// local checkout and the declared ones otherwise.
+    pub fn proposed(&self) -> Vec<&String> {
+        match &self.inferred {
+            Some(inferred) => inferred.keys().collect(),
+            None => self.declared.iter().collect(),
+        }
+    }
+}
+
+impl Display for DependencyDiff {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "{}:", self.key)?;
+        let Some(inferred) = &self.inferred else {
+            return writeln!(f, "  ? no local checkout found");
+        };
+        for key in self.additions() {
+            let packages = inferred[key].iter().cloned().collect::<Vec<_>>();
+            writeln!(f, "  + {key} (via {})", packages.join(", "))?;
+        }
+        for key in self.stale() {
+            writeln!(f, "  - {key} (stale)")?;
+        }
+        let proposed = self
+            .proposed()
+            .iter()
+            .map(|key| format!("{key:?}"))
+            .collect::<Vec<_>>();
+        writeln!(f, "  dependencies = [{}]", proposed.join(", "))
+    }
+}
+
+/// Infers dependencies of every repo in the manifest from their local checkouts and compares them
+/// with the dependencies declared in the manifest.
+///
+/// The checkout of a repo is expected to be at `<checkouts_dir>/<repo name>`. Package dependencies
+/// are mapped back to repos in the manifest either by their git URL, or by the published package
+/// names of the repos. Published package names are taken from `crates` settings of the repos and
+/// from the manifests found in their checkouts.
+pub fn infer_dependencies(
+    manifest: &Manifest,
+    checkouts_dir: &Path,
+) -> Result<Vec<DependencyDiff>, InferError> {
+    let mut checkouts = BTreeMap::new();
+    for (key, entry) in &manifest.repo {
+        let checkout_path = checkouts_dir.join(entry.details().name());
+        if checkout_path.is_dir() {
+            checkouts.insert(key.as_str(), Checkout::scan(&checkout_path)?);
+        }
+    }
+
+    // Map published package names to the repos providing them.
+    let mut package_to_key = BTreeMap::new();
+    for (key, checkout) in &checkouts {
+        for package in checkout.packages() {
+            package_to_key.insert(package.clone(), *key);
+        }
+    }
+    for (key, entry) in &manifest.repo {
+        for package in manifest.settings(entry.details()).crates() {
+            package_to_key.insert(package.clone(), key.as_str());
+        }
+    }
+
+    let diffs = manifest
+        .repo
+        .iter()
+        .map(|(key, entry)| {
+            let declared = entry
+                .dependencies()
+                .map(|dependency| dependency.name().to_string())
+                .collect();
+            let inferred = checkouts.get(key.as_str()).map(|checkout| {
+                let mut inferred: BTreeMap<String, BTreeSet<String>> = BTreeMap::new();
+                for dependency in checkout.dependencies() {
+                    let upstream_key = dependency
+                        .git()
+                        .and_then(|git| key_of_git_url(manifest, git))
+                        .or_else(|| package_to_key.get(dependency.name()).copied());
+                    match upstream_key {
+                        Some(upstream_key) if upstream_key != key => {
+                            inferred
+                                .entry(upstream_key.to_string())
+                                .or_default()
+                                .insert(dependency.name().to_string());
+                        }
+                        _ => {}
+                    }
+                }
+                inferred
+            });
+            DependencyDiff {
+                key: key.clone(),
+                declared,
+                inferred,
+            }
+        })
+        .collect();
+    Ok(diffs)
+}
+
+/// Returns the key of the repo in the manifest hosted at the given git URL.
+fn key_of_git_url<'a>(manifest: &'a Manifest, git_url: &str) -> Option<&'a str> {
+    let (owner, name) = owner_and_name_of_git_url(git_url)?;
+    manifest
+        .repo
+        .iter()
+        .find(|(_, entry)| repo_matches(entry.details(), &owner, &name))
+        .map(|(key, _)| key.as_str())
+}
+
+fn repo_matches(repo: &Repo, owner: &str, name: &str) -> bool {
+    repo.owner().eq_ignore_ascii_case(owner) && repo.name().eq_ignore_ascii_case(name)
+}
+
+/// Extracts owner and name of the repo from git URLs such as
+/// `git+https://github.com/FuelLabs/fuel-core?branch=master#<hash>` or
+/// `git@github.com:FuelLabs/sway.git`.
+fn owner_and_name_of_git_url(git_url: &str) -> Option<(String, String)> {
+    let url = git_url.trim_start_matches("git+");
+    let url = url.split(['?', '#']).next()?;
+    let url = url.trim_end_matches('/').trim_end_matches(".git");
+    let mut segments = url.rsplit(['/', ':']);
+    let name = segments.next()?;
+    let owner = segments.next()?;
+    if name.is_empty() || owner.is_empty() {
+        return None;
+    }
+    Some((owner.to_string(), name.to_string()))
+}
+
+/// Collects git sources of the packages in a `Cargo.lock`.
+fn collect_lock_sources(table: &Table, lock_sources: &mut BTreeMap<String, String>) {
+    let packages = table.get("package").and_then(Value::as_array);
+    for package in packages.into_iter().flatten().filter_map(Value::as_table) {
+        let name = package.get("name").and_then(Value::as_str);
+        let source = package.get("source").and_then(Value::as_str);
+        if let (Some(name), Some(source)) = (name, source) {
+            if source.starts_with("git+") {
+                lock_sources.insert(name.to_string(), source.to_string());
+            }
+        }
+    }
+}
+
+/// Returns the paths of the package manifests and lock files under `path`, skipping hidden and
+/// build output directories.
+fn manifest_files(path: &Path) -> Result<Vec<PathBuf>, InferError> {
+    let mut files = vec![];
+    let entries = std::fs::read_dir(path)
+        .map_err(|e| InferError::FailedToReadCheckout(format!("{path:?}"), e))?;
+    for entry in entries {
+        let entry = entry.map_err(|e| InferError::FailedToReadCheckout(format!("{path:?}"), e))?;
+        let entry_path = entry.path();
+        let file_name = entry.file_name();
+        let file_name = file_name.to_string_lossy();
+        if entry_path.is_dir() {
+            if !file_name.starts_with('.') && !IGNORED_DIRS.contains(&file_name.as_ref()) {
+                files.extend(manifest_files(&entry_path)?);
+            }
+        } else if [
+            CARGO_MANIFEST_FILE_NAME,
+            CARGO_LOCK_FILE_NAME,
+            FORC_MANIFEST_FILE_NAME,
+        ]
+        .contains(&file_name.as_ref())
+        {
+            files.push(entry_path);
+        }
+    }
+    files.sort();
+    Ok(files)
+}
+
+fn read_toml(path: &Path) -> Result<Table, InferError> {
+    let content = std::fs::read_to_string(path)
+        .map_err(|e| InferError::FailedToReadCheckout(format!("{path:?}"), e))?;
+    content
+        .parse::<Table>()
+        .map_err(|e| InferError::FailedToParsePackageManifest(format!("{path:?}"), e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{infer_dependencies, owner_and_name_of_git_url, Checkout};
+    use crate::manifest::ManifestFile;
+    use std::path::PathBuf;
+
+    const INFER_TEST_MANIFEST_FILE_NAME: &str = "repo-plan-infer.toml";
+
+    fn checkouts_dir() -> PathBuf {
+        PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+            .join("tests")
+            .join("checkouts")
+    }
+
+    #[test]
+    fn parse_owner_and_name_of_git_urls() {
+        let expected = Some(("FuelLabs".to_string(), "fuel-core".to_string()));
+        assert_eq!(
+            owner_and_name_of_git_url(
+                "git+https://github.com/FuelLabs/fuel-core?branch=master#abc"
+            ),
+            expected
+        );
+        assert_eq!(
+            owner_and_name_of_git_url("https://github.com/FuelLabs/fuel-core.git"),
+            expected
+        );
+        assert_eq!(
+            owner_and_name_of_git_url("git@github.com:FuelLabs/fuel-core.git"),
+            expected
+        );
+    }
+
+    #[test]
+    fn scan_cargo_workspace_checkout() {
+        let checkout = Checkout::scan(&checkouts_dir().join("fuels-rs")).unwrap();
+        let packages = checkout.packages().collect::<Vec<_>>();
+        assert_eq!(packages, vec!["fuels", "fuels-core"]);
+
+        let dependency_names = checkout
+            .dependencies()
+            .map(|dependency| dependency.name())
+            .collect::<Vec<_>>();
+        assert!(dependency_names.contains(&"fuel-core-client"));
+        assert!(dependency_names.contains(&"forc-pkg"));
+    }
+
+    #[test]
+    fn infer_dependencies_from_checkouts() {
+        let manifest_path = checkouts_dir().join(INFER_TEST_MANIFEST_FILE_NAME);
+        let manifest = ManifestFile::from_file(&manifest_path).unwrap().manifest();
+        let diffs = infer_dependencies(&manifest, &checkouts_dir()).unwrap();
+
+        let fuel_core = diffs.iter().find(|diff| diff.key() == "fuel-core").unwrap();
+        assert!(fuel_core.is_in_sync());
+
+        // `fuels-rs` depends on `sway` through a git source in its lock file and on `fuel-core`
+        // through its published crates, while the manifest only declares the stale `wallet`
+        // dependency.
+        let fuels_rs = diffs.iter().find(|diff| diff.key() == "fuels-rs").unwrap();
+        assert_eq!(
+            fuels_rs.additions().collect::<Vec<_>>(),
+            vec!["fuel-core", "sway"]
+        );
+        assert_eq!(fuels_rs.stale().collect::<Vec<_>>(), vec!["wallet"]);
+
+        // `sway` depends on `fuel-core` through a target specific workspace dependency.
+        let sway = diffs.iter().find(|diff| diff.key() == "sway").unwrap();
+        assert!(sway.is_in_sync());
+        assert_eq!(sway.proposed(), vec!["fuel-core"]);
+
+        // `sway-libs` depends on `sway` through a forc git dependency.
+        let sway_libs = diffs.iter().find(|diff| diff.key() == "sway-libs").unwrap();
+        assert!(sway_libs.is_in_sync());
+
+        let wallet = diffs.iter().find(|diff| diff.key() == "wallet").unwrap();
+        assert!(wallet.missing_checkout());
+        assert!(wallet.is_in_sync());
+    }
+}
@@ -1,4 +1,7 @@
 //! A utility library for creating build plans across different repos.
+pub mod consistency;
 pub mod error;
+pub mod infer;
 pub mod manifest;
 pub mod plan;
+pub mod source;
@@ -1,15 +1,37 @@
-use releasy_core::repo::Repo;
+use releasy_core::{
+    default::{DEFAULT_COMMIT_AUTHOR_EMAIL, DEFAULT_COMMIT_AUTHOR_NAME},
+    delivery::Delivery,
+    event::EventType,
+    repo::Repo,
+};
 use serde::{Deserialize, Serialize};
-use std::{collections::BTreeMap, path::Path};
+use std::{
+    collections::BTreeMap,
+    env::current_dir,
+    fmt::Display,
+    path::{Path, PathBuf},
+};
 
-use crate::error::ManifestFileError;
+use crate::{
+    error::ManifestFileError,
+    source::{resolve_extends, EXTENDS_KEY},
+};
 
 /// A toml manifest file describing relations between different repositories.
+///
+/// A manifest can extend a shared plan file with an `[extends]` table (see `PlanSource`), in which
+/// case it only needs to declare `current-repo` and the values overriding the shared plan.
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
 #[serde(rename_all = "kebab-case")]
 pub struct Manifest {
+    #[serde(default)]
     pub(crate) repo: BTreeMap<String, RepoEntry>,
     pub(crate) current_repo: Repo,
+    /// Settings inherited by every repo that does not override them.
+    pub(crate) defaults: Option<RepoSettings>,
+    /// Custom event types handled by the repos, mapped to the action taken when they are received.
+    #[serde(default)]
+    pub(crate) events: BTreeMap<String, EventAction>,
 }
 
 impl Manifest {
@@ -17,6 +39,42 @@ impl Manifest {
     pub fn current_repo(&self) -> &Repo {
         &self.current_repo
     }
+
+    /// Returns an iterator over the repos declared in the manifest.
+    pub fn repos(&self) -> impl Iterator<Item = &Repo> {
+        self.repo.values().map(|entry| entry.details())
+    }
+
+    /// Returns the entry describing the given repo, if it is present in the manifest.
+    pub fn repo_entry(&self, repo: &Repo) -> Option<&RepoEntry> {
+        self.repo.values().find(|entry| &entry.details == repo)
+    }
+
+    /// Returns the action declared for the given custom event type, if any.
+    pub fn event_action(&self, event_type: &EventType) -> Option<&EventAction> {
+        match event_type {
+            EventType::Custom(name) => self.events.get(name),
+            _ => None,
+        }
+    }
+
+    /// Returns an iterator over the declared custom event types and their actions.
+    pub fn event_actions(&self) -> impl Iterator<Item = (&String, &EventAction)> {
+        self.events.iter()
+    }
+
+    /// Returns the settings of the given repo, with the settings missing from its entry inherited
+    /// from the `[defaults]` table.
+    pub fn settings(&self, repo: &Repo) -> RepoSettings {
+        let settings = self
+            .repo_entry(repo)
+            .and_then(|entry| entry.settings.clone())
+            .unwrap_or_default();
+        match &self.defaults {
+            Some(defaults) => settings.inherit(defaults),
+            None => settings,
+        }
+    }
 }
 
 #[derive(Clone, Debug, PartialEq, Eq)]
@@ -40,34 +98,66 @@ impl ManifestFile {
     pub fn from_file(path: &Path) -> Result<ManifestFile, ManifestFileError> {
         let manifest_str = std::fs::read_to_string(path)
             .map_err(|e| ManifestFileError::MissingManifestFile(format!("{path:?}"), e))?;
-        ManifestFile::try_from(manifest_str)
+        let manifest_dir = path.parent().map(Path::to_path_buf).unwrap_or_default();
+        ManifestFile::from_str_in_dir(manifest_str, &manifest_dir)
     }
-}
-
-impl TryFrom<String> for ManifestFile {
-    type Error = ManifestFileError;
 
-    fn try_from(value: String) -> Result<Self, Self::Error> {
+    /// Parses a manifest, resolving the plan it extends relative to `manifest_dir`.
+    fn from_str_in_dir(value: String, manifest_dir: &Path) -> Result<Self, ManifestFileError> {
         let mut warnings = vec![];
-        let toml_de = toml::de::Deserializer::new(&value);
-        let manifest: Manifest = serde_ignored::deserialize(toml_de, |path| {
+        let mut push_warning = |path: serde_ignored::Path| {
             let warning = format!("  WARNING! unused manifest key: {path}");
             warnings.push(warning);
-        })
-        .map_err(ManifestFileError::FailedToParseManifest)?;
+        };
+
+        let mut fetch_warnings = vec![];
+        let table = value
+            .parse::<toml::Table>()
+            .map_err(ManifestFileError::FailedToParseManifest)?;
+        let manifest: Manifest = if table.contains_key(EXTENDS_KEY) {
+            let table = resolve_extends(table, manifest_dir, &mut fetch_warnings)?;
+            serde_ignored::deserialize(toml::Value::Table(table), &mut push_warning)
+                .map_err(ManifestFileError::FailedToParseManifest)?
+        } else {
+            // Deserialize from the string directly, so that errors point to their location.
+            let toml_de = toml::de::Deserializer::new(&value);
+            serde_ignored::deserialize(toml_de, &mut push_warning)
+                .map_err(ManifestFileError::FailedToParseManifest)?
+        };
 
+        if let Some(name) = manifest
+            .events
+            .keys()
+            .find(|name| !EventType::is_valid_custom_name(name))
+        {
+            return Err(ManifestFileError::InvalidCustomEventName(name.clone()));
+        }
+
+        warnings.extend(fetch_warnings);
         let manifest_with_warnings = ManifestFile { warnings, manifest };
         Ok(manifest_with_warnings)
     }
 }
 
+impl TryFrom<String> for ManifestFile {
+    type Error = ManifestFileError;
+
+    /// Parses a manifest, resolving the plan it extends relative to the current directory.
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        let manifest_dir = current_dir().unwrap_or_else(|_| PathBuf::from("."));
+        ManifestFile::from_str_in_dir(value, &manifest_dir)
+    }
+}
+
 /// A repository entry in the manifest, describing a repository and its dependencies.
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
 #[serde(rename_all = "kebab-case")]
 pub struct RepoEntry {
     pub(crate) details: Repo,
     /// Dependencies of this repo
-    dependencies: Option<Vec<String>>,
+    dependencies: Option<Vec<Dependency>>,
+    /// Optional settings of this repo.
+    pub(crate) settings: Option<RepoSettings>,
 }
 
 impl RepoEntry {
@@ -77,14 +167,611 @@ impl RepoEntry {
     }
 
     /// Returns an iterator over dependencies decribed in this `Project`.
-    pub fn dependencies(&self) -> impl Iterator<Item = &String> {
+    pub fn dependencies(&self) -> impl Iterator<Item = &Dependency> {
         self.dependencies.iter().flatten()
     }
 }
 
+/// Action taken by a handler receiving a custom event, declared as:
+///
+/// ```toml
+/// [events.docs-published]
+/// action = "refresh-tracking-branch"
+///
+/// [events.audit-requested]
+/// action = "hook"
+/// script = "./scripts/audit.sh"
+/// ```
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+#[serde(tag = "action", rename_all = "kebab-case")]
+pub enum EventAction {
+    /// Pushes a new commit to the tracking branch of the source repo, same as for
+    /// `new-commit-to-dependency` events.
+    RefreshTrackingBranch,
+    /// Runs a shell script in a checkout of the current repo.
+    Hook { script: String },
+}
+
+/// Optional settings of a repo. Any setting left out is inherited from the `[defaults]` table of
+/// the manifest.
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub struct RepoSettings {
+    /// Default branch of the repo. Inferred from the remote if omitted.
+    default_branch: Option<String>,
+    /// Long-lived release branches that are tracked in addition to the default branch.
+    release_branches: Option<Vec<String>>,
+    /// URL used to clone the repo instead of the GitHub URL derived from its details.
+    clone_url: Option<String>,
+    /// Package ecosystem of the repo.
+    ecosystem: Option<Ecosystem>,
+    /// Names of the packages published from the repo.
+    crates: Option<Vec<String>>,
+    /// Settings controlling how events are handled in the repo.
+    handler: Option<HandlerSettings>,
+    /// Names of the groups the repo belongs to, used for selecting repos by group.
+    groups: Option<Vec<String>>,
+    /// How events are delivered to the repo.
+    delivery: Option<Delivery>,
+}
+
+impl RepoSettings {
+    /// Returns the default branch of the repo, if it is declared.
+    pub fn default_branch(&self) -> Option<&str> {
+        self.default_branch.as_deref()
+    }
+
+    /// Returns an iterator over release branches tracked in addition to the default branch.
+    pub fn release_branches(&self) -> impl Iterator<Item = &String> {
+        self.release_branches.iter().flatten()
+    }
+
+    /// Returns the URL overriding the GitHub URL of the repo, if it is declared.
+    pub fn clone_url(&self) -> Option<&str> {
+        self.clone_url.as_deref()
+    }
+
+    /// Returns the package ecosystem of the repo, `cargo` by default.
+    pub fn ecosystem(&self) -> Ecosystem {
+        self.ecosystem.clone().unwrap_or_default()
+    }
+
+    /// Returns an iterator over the names of the packages published from the repo.
+    pub fn crates(&self) -> impl Iterator<Item = &String> {
+        self.crates.iter().flatten()
+    }
+
+    /// Returns the handler settings of the repo.
+    pub fn handler(&self) -> HandlerSettings {
+        self.handler.clone().unwrap_or_default()
+    }
+
+    /// Returns an iterator over the names of the groups the repo belongs to.
+    pub fn groups(&self) -> impl Iterator<Item = &String> {
+        self.groups.iter().flatten()
+    }
+
+    /// Returns how events are delivered to the repo, `repository-dispatch` by default.
+    ///
+    /// Workflows triggered with `workflow-dispatch` run on the default branch of the repo, unless
+    /// a ref is declared.
+    pub fn delivery(&self) -> Delivery {
+        match self.delivery.clone().unwrap_or_default() {
+            Delivery::WorkflowDispatch {
+                workflow,
+                git_ref: None,
+            } => Delivery::WorkflowDispatch {
+                workflow,
+                git_ref: self.default_branch.clone(),
+            },
+            delivery => delivery,
+        }
+    }
+
+    /// Fills the settings missing from `self` with the ones from `defaults`.
+    pub fn inherit(self, defaults: &RepoSettings) -> RepoSettings {
+        let handler = match (self.handler, &defaults.handler) {
+            (Some(handler), Some(default_handler)) => Some(handler.inherit(default_handler)),
+            (handler, default_handler) => handler.or_else(|| default_handler.clone()),
+        };
+        RepoSettings {
+            default_branch: self
+                .default_branch
+                .or_else(|| defaults.default_branch.clone()),
+            release_branches: self
+                .release_branches
+                .or_else(|| defaults.release_branches.clone()),
+            clone_url: self.clone_url.or_else(|| defaults.clone_url.clone()),
+            ecosystem: self.ecosystem.or_else(|| defaults.ecosystem.clone()),
+            crates: self.crates.or_else(|| defaults.crates.clone()),
+            handler,
+            groups: self.groups.or_else(|| defaults.groups.clone()),
+            delivery: self.delivery.or_else(|| defaults.delivery.clone()),
+        }
+    }
+}
+
+/// Package ecosystem of a repo.
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum Ecosystem {
+    #[default]
+    Cargo,
+    Forc,
+    Npm,
+}
+
+/// Settings controlling how `releasy-handler` behaves in a repo.
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub struct HandlerSettings {
+    /// Name used for the commits created by the handler.
+    commit_author_name: Option<String>,
+    /// Email used for the commits created by the handler.
+    commit_author_email: Option<String>,
+    /// Whether tracking branches are force pushed, `true` by default.
+    force_push: Option<bool>,
+    /// How the CI of tracking branches is triggered.
+    ci_trigger: Option<CiTrigger>,
+    /// How tracking branches are brought up to date with the default branch.
+    branch_strategy: Option<BranchStrategy>,
+    /// Shell commands run on a tracking branch after it is brought up to date.
+    hooks: Option<Vec<String>>,
+    /// Whether git sources of the upstream are pinned to the commit of the event.
+    pin_upstream_commit: Option<bool>,
+    /// Whether a `releasy/<repo>` commit status is posted on upstream commits being handled.
+    report_commit_status: Option<bool>,
+}
+
+/// How the handler brings a tracking branch up to date with the default branch.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum BranchStrategy {
+    /// The history of the tracking branch is rebased onto the default branch.
+    #[default]
+    Rebase,
+    /// The tracking branch is reset to the default branch and the patch spec of the edge is
+    /// applied again, so that it only depends on the default branch and the manifest.
+    Recreate,
+}
+
+/// How the handler triggers the CI of a tracking branch for a new upstream commit.
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, Eq)]
+#[serde(tag = "mode", rename_all = "kebab-case")]
+pub enum CiTrigger {
+    /// An empty commit is added to the tracking branch for every event.
+    #[default]
+    EmptyCommit,
+    /// A single commit owned by releasy is kept on top of the tracking branch, and amended for
+    /// every event.
+    RollingCommit,
+    /// The given workflow is dispatched on the tracking branch, without any commit.
+    WorkflowRun { workflow: String },
+}
+
+impl HandlerSettings {
+    /// Returns the name used for the commits created by the handler.
+    pub fn commit_author_name(&self) -> &str {
+        self.commit_author_name
+            .as_deref()
+            .unwrap_or(DEFAULT_COMMIT_AUTHOR_NAME)
+    }
+
+    /// Returns the email used for the commits created by the handler.
+    pub fn commit_author_email(&self) -> &str {
+        self.commit_author_email
+            .as_deref()
+            .unwrap_or(DEFAULT_COMMIT_AUTHOR_EMAIL)
+    }
+
+    /// Returns true if tracking branches should be force pushed.
+    pub fn force_push(&self) -> bool {
+        self.force_push.unwrap_or(true)
+    }
+
+    /// Returns how tracking branches are brought up to date, rebased by default.
+    pub fn branch_strategy(&self) -> BranchStrategy {
+        self.branch_strategy.unwrap_or_default()
+    }
+
+    /// Returns how the CI of tracking branches is triggered, with an empty commit by default.
+    pub fn ci_trigger(&self) -> CiTrigger {
+        self.ci_trigger.clone().unwrap_or_default()
+    }
+
+    /// Returns true if the git sources of the upstream should be pinned to the commit of each event
+    /// on tracking branches, instead of following the upstream branch.
+    pub fn pin_upstream_commit(&self) -> bool {
+        self.pin_upstream_commit.unwrap_or(false)
+    }
+
+    /// Returns true if a pending commit status should be posted on upstream commits when their
+    /// events are handled.
+    pub fn report_commit_status(&self) -> bool {
+        self.report_commit_status.unwrap_or(false)
+    }
+
+    /// Returns an iterator over the commands run on tracking branches after they are updated.
+    pub fn hooks(&self) -> impl Iterator<Item = &String> {
+        self.hooks.iter().flatten()
+    }
+
+    /// Fills the settings missing from `self` with the ones from `defaults`.
+    pub fn inherit(self, defaults: &HandlerSettings) -> HandlerSettings {
+        HandlerSettings {
+            commit_author_name: self
+                .commit_author_name
+                .or_else(|| defaults.commit_author_name.clone()),
+            commit_author_email: self
+                .commit_author_email
+                .or_else(|| defaults.commit_author_email.clone()),
+            force_push: self.force_push.or(defaults.force_push),
+            ci_trigger: self.ci_trigger.or_else(|| defaults.ci_trigger.clone()),
+            branch_strategy: self.branch_strategy.or(defaults.branch_strategy),
+            hooks: self.hooks.or_else(|| defaults.hooks.clone()),
+            pin_upstream_commit: self.pin_upstream_commit.or(defaults.pin_upstream_commit),
+            report_commit_status: self.report_commit_status.or(defaults.report_commit_status),
+        }
+    }
+}
+
+/// A dependency of a repo, either declared with just the key of the upstream repo:
+///
+/// ```toml
+/// dependencies = ["fuel-core"]
+/// ```
+///
+/// or as a table describing how events propagate along the edge:
+///
+/// ```toml
+/// dependencies = [
+///   { name = "sway", events = ["new-commit-to-dependency"], paths = ["sway-lib-std/**"] },
+/// ]
+/// ```
+#[derive(Serialize, Clone, Debug, PartialEq, Eq)]
+#[serde(untagged)]
+pub enum Dependency {
+    Simple(String),
+    Detailed(DependencyDetails),
+}
+
+impl<'de> Deserialize<'de> for Dependency {
+    /// Dispatches on the type of the value instead of deriving an untagged enum, so that errors in
+    /// detailed dependencies, such as misspelled keys, are reported instead of a mismatch.
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct DependencyVisitor;
+
+        impl<'de> serde::de::Visitor<'de> for DependencyVisitor {
+            type Value = Dependency;
+
+            fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+                formatter.write_str("a repo key or a table of dependency details")
+            }
+
+            fn visit_str<E: serde::de::Error>(self, name: &str) -> Result<Self::Value, E> {
+                Ok(Dependency::Simple(name.to_string()))
+            }
+
+            fn visit_map<A: serde::de::MapAccess<'de>>(
+                self,
+                map: A,
+            ) -> Result<Self::Value, A::Error> {
+                DependencyDetails::deserialize(serde::de::value::MapAccessDeserializer::new(map))
+                    .map(Dependency::Detailed)
+            }
+        }
+
+        deserializer.deserialize_any(DependencyVisitor)
+    }
+}
+
+impl Dependency {
+    /// Returns the key of the upstream repo in the manifest.
+    pub fn name(&self) -> &str {
+        match self {
+            Dependency::Simple(name) => name,
+            Dependency::Detailed(details) => &details.name,
+        }
+    }
+
+    /// Returns the edge details of this dependency. Simple dependencies are converted into
+    /// details with every field left to its default.
+    pub fn details(&self) -> DependencyDetails {
+        match self {
+            Dependency::Simple(name) => DependencyDetails::new(name.clone()),
+            Dependency::Detailed(details) => details.clone(),
+        }
+    }
+}
+
+/// Describes an edge between an upstream repo and the repo depending on it.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct DependencyDetails {
+    /// Key of the upstream repo in the manifest.
+    name: String,
+    /// Event types propagated along this edge. All event types are propagated if omitted.
+    events: Option<Vec<EventType>>,
+    /// Mapping from upstream branch names to the downstream tracking branch names.
+    ///
+    /// If omitted, only the default branch of the upstream is tracked with
+    /// `upgrade/<upstream-name>-master`.
+    branches: Option<BTreeMap<String, String>>,
+    /// Mapping from upstream branch names to the downstream branches their tracking branches are
+    /// based on, e.g. to track a release branch of the upstream on top of a release branch of the
+    /// current repo.
+    ///
+    /// Tracking branches of upstream branches that are not listed are based on the default branch.
+    base_branches: Option<BTreeMap<String, String>>,
+    /// Glob patterns of upstream paths. A commit is only propagated along this edge if it touches
+    /// at least one of them. All commits are propagated if omitted.
+    paths: Option<Vec<String>>,
+    /// Changes applied to tracking branches regenerated with the `recreate` branch strategy.
+    patch: Option<PatchSpec>,
+    /// Shell commands run on the tracking branches of this edge after they are brought up to
+    /// date, after the hooks of the repo.
+    hooks: Option<Vec<String>>,
+}
+
+/// Declarative description of the changes making a tracking branch use the upstream's tracked
+/// branch, applied on top of the default branch by the `recreate` branch strategy:
+///
+/// ```toml
+/// dependencies = [
+///   { name = "fuel-core", patch = { crates = ["fuel-core-client"], files = [{ path = "ci.env", contents = "FUEL_CORE_PATCHED=1\n" }] } },
+/// ]
+/// ```
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub struct PatchSpec {
+    /// Crates of the upstream repo patched to its git source. Defaults to the `crates` declared
+    /// in the settings of the upstream repo.
+    crates: Option<Vec<String>>,
+    /// Files written to the tracking branch, replacing their contents.
+    #[serde(default)]
+    files: Vec<FileEdit>,
+}
+
+impl PatchSpec {
+    /// Returns the patched crates, if they are declared explicitly.
+    pub fn crates(&self) -> Option<&[String]> {
+        self.crates.as_deref()
+    }
+
+    pub fn files(&self) -> impl Iterator<Item = &FileEdit> {
+        self.files.iter()
+    }
+}
+
+/// A file written to a tracking branch, relative to the root of the repo.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct FileEdit {
+    path: String,
+    contents: String,
+}
+
+impl FileEdit {
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    pub fn contents(&self) -> &str {
+        &self.contents
+    }
+}
+
+impl DependencyDetails {
+    /// Creates edge details for the given upstream key with every field left to its default.
+    pub fn new(name: String) -> Self {
+        Self {
+            name,
+            events: None,
+            branches: None,
+            base_branches: None,
+            paths: None,
+            patch: None,
+            hooks: None,
+        }
+    }
+
+    /// Returns the key of the upstream repo in the manifest.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Replaces the key of the upstream repo.
+    pub(crate) fn with_name(mut self, name: String) -> Self {
+        self.name = name;
+        self
+    }
+
+    /// Returns the patch applied to tracking branches regenerated along this edge.
+    pub fn patch(&self) -> PatchSpec {
+        self.patch.clone().unwrap_or_default()
+    }
+
+    /// Returns an iterator over the event types propagated along this edge, if they are
+    /// restricted.
+    pub fn events(&self) -> impl Iterator<Item = &EventType> {
+        self.events.iter().flatten()
+    }
+
+    /// Returns an iterator over path patterns declared for this edge.
+    pub fn paths(&self) -> impl Iterator<Item = &String> {
+        self.paths.iter().flatten()
+    }
+
+    /// Returns an iterator over the commands run on the tracking branches of this edge.
+    pub fn hooks(&self) -> impl Iterator<Item = &String> {
+        self.hooks.iter().flatten()
+    }
+
+    /// Returns true if the given event type should be propagated along this edge.
+    pub fn subscribes_to(&self, event_type: &EventType) -> bool {
+        self.events
+            .as_ref()
+            .map(|events| events.contains(event_type))
+            .unwrap_or(true)
+    }
+
+    /// Returns true if a commit touching `changed_paths` should be propagated along this edge.
+    ///
+    /// Patterns that are not valid globs never match, `Plan` rejects them while being built.
+    pub fn touches_paths<'a>(&self, changed_paths: impl IntoIterator<Item = &'a str>) -> bool {
+        let Some(paths) = &self.paths else {
+            return true;
+        };
+        let patterns = paths
+            .iter()
+            .filter_map(|pattern| glob::Pattern::new(pattern).ok())
+            .collect::<Vec<_>>();
+        changed_paths
+            .into_iter()
+            .any(|path| patterns.iter().any(|pattern| pattern.matches(path)))
+    }
+
+    /// Returns the name of the downstream tracking branch for a commit to `upstream_branch` of
+    /// `upstream`, whose default branch is `upstream_default_branch`. If no branch is given, the
+    /// default branch is assumed.
+    ///
+    /// Returns `None` if the branch is not tracked along this edge.
+    pub fn tracking_branch(
+        &self,
+        upstream: &Repo,
+        upstream_default_branch: &str,
+        upstream_branch: Option<&str>,
+    ) -> Option<String> {
+        let upstream_branch = upstream_branch.unwrap_or(upstream_default_branch);
+        match &self.branches {
+            Some(branches) => branches.get(upstream_branch).cloned(),
+            None => (upstream_branch == upstream_default_branch)
+                .then(|| Self::default_tracking_branch(upstream)),
+        }
+    }
+
+    /// Returns the names of all downstream tracking branches maintained along this edge.
+    pub fn tracking_branches(&self, upstream: &Repo, upstream_default_branch: &str) -> Vec<String> {
+        self.branch_mapping(upstream, upstream_default_branch)
+            .into_iter()
+            .map(|(_, tracking_branch)| tracking_branch)
+            .collect()
+    }
+
+    /// Returns the tracked upstream branches together with their downstream tracking branch.
+    pub fn branch_mapping(
+        &self,
+        upstream: &Repo,
+        upstream_default_branch: &str,
+    ) -> Vec<(String, String)> {
+        match &self.branches {
+            Some(branches) => branches
+                .iter()
+                .map(|(upstream_branch, tracking_branch)| {
+                    (upstream_branch.clone(), tracking_branch.clone())
+                })
+                .collect(),
+            None => vec![(
+                upstream_default_branch.to_string(),
+                Self::default_tracking_branch(upstream),
+            )],
+        }
+    }
+
+    /// Returns the downstream branch the tracking branch of `upstream_branch` is based on, if it is
+    /// not the default branch.
+    pub fn base_branch(&self, upstream_branch: &str) -> Option<&str> {
+        self.base_branches
+            .as_ref()
+            .and_then(|base_branches| base_branches.get(upstream_branch))
+            .map(String::as_str)
+    }
+
+    /// Returns the tracked upstream branches together with their downstream tracking branch and
+    /// the downstream branch it is based on, `None` standing for the default branch.
+    pub fn tracking_bases(
+        &self,
+        upstream: &Repo,
+        upstream_default_branch: &str,
+    ) -> Vec<(String, String, Option<String>)> {
+        self.branch_mapping(upstream, upstream_default_branch)
+            .into_iter()
+            .map(|(upstream_branch, tracking_branch)| {
+                let base_branch = self.base_branch(&upstream_branch).map(str::to_string);
+                (upstream_branch, tracking_branch, base_branch)
+            })
+            .collect()
+    }
+
+    /// Returns an iterator over the upstream branches with a declared base branch.
+    pub fn based_upstream_branches(&self) -> impl Iterator<Item = &String> {
+        self.base_branches.iter().flat_map(BTreeMap::keys)
+    }
+
+    /// The tracking branch used for edges without an explicit branch mapping.
+    fn default_tracking_branch(upstream: &Repo) -> String {
+        format!("upgrade/{}-master", upstream.name())
+    }
+
+    /// Returns the name of the downstream branch tracking a pull request of `upstream`.
+    pub fn pull_request_branch(upstream: &Repo, number: u64) -> String {
+        format!("upgrade/{}-pr-{number}", upstream.name())
+    }
+
+    /// Returns true if the branch tracks a pull request of an upstream repo, rather than one of
+    /// its branches.
+    pub fn is_pull_request_branch(branch: &str) -> bool {
+        branch
+            .strip_prefix("upgrade/")
+            .and_then(|branch| branch.rsplit_once("-pr-"))
+            .is_some_and(|(upstream, number)| {
+                !upstream.is_empty()
+                    && !number.is_empty()
+                    && number.chars().all(|c| c.is_ascii_digit())
+            })
+    }
+}
+
+impl Display for DependencyDetails {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mut fields = vec![];
+        if let Some(events) = &self.events {
+            let events = events
+                .iter()
+                .map(|event| event.to_string())
+                .collect::<Vec<_>>();
+            fields.push(format!("events = {events:?}"));
+        }
+        if let Some(branches) = &self.branches {
+            fields.push(format!("branches = {branches:?}"));
+        }
+        if let Some(base_branches) = &self.base_branches {
+            fields.push(format!("base-branches = {base_branches:?}"));
+        }
+        if let Some(paths) = &self.paths {
+            fields.push(format!("paths = {paths:?}"));
+        }
+        if self.patch.is_some() {
+            fields.push("patch".to_string());
+        }
+        if let Some(hooks) = &self.hooks {
+            fields.push(format!("hooks = {hooks:?}"));
+        }
+        if fields.is_empty() {
+            write!(f, "{{ defaults }}")
+        } else {
+            write!(f, "{{ {} }}", fields.join(", "))
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
-    use super::ManifestFile;
+    use super::{
+        BranchStrategy, CiTrigger, Dependency, DependencyDetails, Ecosystem, EventAction,
+        ManifestFile,
+    };
+    use crate::error::ManifestFileError;
+    use releasy_core::{delivery::Delivery, event::EventType, repo::Repo};
 
     #[test]
     fn parse_manifest_file_no_dependencies() {
@@ -124,4 +811,393 @@ owner = "FuelLabs"
         let parsed = ManifestFile::try_from(manifest_str.to_string()).is_ok();
         assert!(parsed)
     }
+
+    #[test]
+    fn parse_manifest_file_detailed_dependencies() {
+        let manifest_str = r#"
+[current-repo]
+name = "fuels-rs"
+owner = "FuelLabs"
+
+[repo.sway.details]
+name = "sway"
+owner = "FuelLabs"
+
+[repo.fuel-core.details]
+name = "fuel-core"
+owner = "FuelLabs"
+
+[repo.fuels-rs.details]
+name = "fuels-rs"
+owner = "FuelLabs"
+
+[repo.fuels-rs]
+dependencies = [
+  "fuel-core",
+  { name = "sway", events = ["new-commit-to-dependency"], branches = { master = "upgrade/sway-master", "release/v0.46" = "upgrade/sway-v0.46" }, base-branches = { "release/v0.46" = "release/v0.20" }, paths = ["sway-lib-std/**", "forc-pkg/**"] },
+]
+"#;
+
+        let manifest_file = ManifestFile::try_from(manifest_str.to_string()).unwrap();
+        assert_eq!(manifest_file.warnings().count(), 0);
+        let manifest = manifest_file.manifest();
+        let dependencies: Vec<_> = manifest.repo["fuels-rs"].dependencies().collect();
+        assert_eq!(dependencies.len(), 2);
+        assert!(matches!(dependencies[0], Dependency::Simple(_)));
+        assert_eq!(dependencies[1].name(), "sway");
+
+        let sway = Repo::new("sway".to_string(), "FuelLabs".to_string());
+        let details = dependencies[1].details();
+        assert!(details.subscribes_to(&EventType::NewCommitToDependency));
+        assert!(!details.subscribes_to(&EventType::NewRelease));
+        assert!(details.touches_paths(["README.md", "sway-lib-std/src/lib.sw"]));
+        assert!(!details.touches_paths(["docs/book/src/index.md"]));
+        assert_eq!(
+            details.tracking_branch(&sway, "master", None).as_deref(),
+            Some("upgrade/sway-master")
+        );
+        assert_eq!(
+            details
+                .tracking_branch(&sway, "master", Some("release/v0.46"))
+                .as_deref(),
+            Some("upgrade/sway-v0.46")
+        );
+        assert_eq!(
+            details.tracking_branch(&sway, "master", Some("feature")),
+            None
+        );
+        assert_eq!(details.base_branch("master"), None);
+        assert_eq!(details.base_branch("release/v0.46"), Some("release/v0.20"));
+        assert_eq!(
+            details.tracking_bases(&sway, "master"),
+            vec![
+                (
+                    "master".to_string(),
+                    "upgrade/sway-master".to_string(),
+                    None
+                ),
+                (
+                    "release/v0.46".to_string(),
+                    "upgrade/sway-v0.46".to_string(),
+                    Some("release/v0.20".to_string())
+                ),
+            ]
+        );
+
+        let fuel_core = Repo::new("fuel-core".to_string(), "FuelLabs".to_string());
+        let details = dependencies[0].details();
+        assert!(details.subscribes_to(&EventType::NewRelease));
+        assert!(details.touches_paths(["docs/book/src/index.md"]));
+        assert_eq!(
+            details.tracking_branches(&fuel_core, "master"),
+            vec!["upgrade/fuel-core-master".to_string()]
+        );
+        // Without a branch mapping only the default branch of the upstream is tracked, whatever
+        // its name.
+        assert_eq!(
+            details.branch_mapping(&fuel_core, "main"),
+            vec![("main".to_string(), "upgrade/fuel-core-master".to_string())]
+        );
+        assert_eq!(
+            details.tracking_branch(&fuel_core, "main", None).as_deref(),
+            Some("upgrade/fuel-core-master")
+        );
+        assert_eq!(
+            details
+                .tracking_branch(&fuel_core, "main", Some("main"))
+                .as_deref(),
+            Some("upgrade/fuel-core-master")
+        );
+        assert_eq!(
+            details.tracking_branch(&fuel_core, "main", Some("master")),
+            None
+        );
+        assert_eq!(
+            details.tracking_branch(&fuel_core, "main", Some("dependabot/cargo/serde-1.0.190")),
+            None
+        );
+
+        let pull_request_branch = DependencyDetails::pull_request_branch(&fuel_core, 1432);
+        assert_eq!(pull_request_branch, "upgrade/fuel-core-pr-1432");
+        assert!(DependencyDetails::is_pull_request_branch(
+            &pull_request_branch
+        ));
+        assert!(!DependencyDetails::is_pull_request_branch(
+            "upgrade/fuel-core-master"
+        ));
+        assert!(!DependencyDetails::is_pull_request_branch(
+            "upgrade/fuel-core-pr-next"
+        ));
+    }
+
+    #[test]
+    fn reject_misspelled_dependency_keys() {
+        let manifest_str = r#"
+[current-repo]
+name = "fuels-rs"
+owner = "FuelLabs"
+
+[repo.sway.details]
+name = "sway"
+owner = "FuelLabs"
+
+[repo.fuels-rs]
+dependencies = [
+  { name = "sway", brnaches = { master = "upgrade/sway-master" } },
+]
+"#;
+
+        let error = ManifestFile::try_from(manifest_str.to_string()).unwrap_err();
+        assert!(
+            error.to_string().contains("unknown field `brnaches`"),
+            "{error}"
+        );
+    }
+
+    #[test]
+    fn parse_manifest_file_patch_spec() {
+        let manifest_str = r#"
+[current-repo]
+name = "fuels-rs"
+owner = "FuelLabs"
+
+[defaults.handler]
+branch-strategy = "recreate"
+
+[repo.fuel-core.details]
+name = "fuel-core"
+owner = "FuelLabs"
+
+[repo.fuels-rs.details]
+name = "fuels-rs"
+owner = "FuelLabs"
+
+[repo.fuels-rs]
+dependencies = [
+  { name = "fuel-core", patch = { crates = ["fuel-core-client"], files = [{ path = "ci.env", contents = "PATCHED=1" }] } },
+]
+"#;
+
+        let manifest_file = ManifestFile::try_from(manifest_str.to_string()).unwrap();
+        assert_eq!(manifest_file.warnings().count(), 0);
+        let manifest = manifest_file.manifest();
+        let fuels_rs = manifest.current_repo().clone();
+        assert_eq!(
+            manifest.settings(&fuels_rs).handler().branch_strategy(),
+            BranchStrategy::Recreate
+        );
+
+        let patch = manifest.repo["fuels-rs"]
+            .dependencies()
+            .next()
+            .unwrap()
+            .details()
+            .patch();
+        assert_eq!(patch.crates(), Some(&["fuel-core-client".to_string()][..]));
+        let files = patch.files().collect::<Vec<_>>();
+        assert_eq!(files[0].path(), "ci.env");
+        assert_eq!(files[0].contents(), "PATCHED=1");
+    }
+
+    #[test]
+    fn parse_manifest_file_hooks() {
+        let manifest_str = r#"
+[current-repo]
+name = "fuels-rs"
+owner = "FuelLabs"
+
+[defaults.handler]
+hooks = ["cargo update --workspace"]
+pin-upstream-commit = true
+report-commit-status = true
+
+[repo.fuel-core.details]
+name = "fuel-core"
+owner = "FuelLabs"
+
+[repo.fuels-rs.details]
+name = "fuels-rs"
+owner = "FuelLabs"
+
+[repo.fuels-rs]
+dependencies = [
+  { name = "fuel-core", hooks = ["cargo update -p fuel-core-client", "./scripts/codegen.sh"] },
+]
+"#;
+
+        let manifest = ManifestFile::try_from(manifest_str.to_string())
+            .unwrap()
+            .manifest();
+        let fuels_rs = manifest.current_repo().clone();
+        let handler = manifest.settings(&fuels_rs).handler();
+        assert_eq!(
+            handler.hooks().collect::<Vec<_>>(),
+            vec!["cargo update --workspace"]
+        );
+        assert!(handler.pin_upstream_commit());
+        assert!(handler.report_commit_status());
+
+        let edge = manifest.repo["fuels-rs"]
+            .dependencies()
+            .next()
+            .unwrap()
+            .details();
+        assert_eq!(
+            edge.hooks().collect::<Vec<_>>(),
+            vec!["cargo update -p fuel-core-client", "./scripts/codegen.sh"]
+        );
+    }
+
+    #[test]
+    fn parse_manifest_file_settings_with_defaults() {
+        let manifest_str = r#"
+[current-repo]
+name = "fuels-rs"
+owner = "FuelLabs"
+
+[defaults]
+default-branch = "master"
+
+[defaults.handler]
+commit-author-name = "fuel-bot"
+force-push = false
+ci-trigger = { mode = "rolling-commit" }
+
+[repo.fuel-core.details]
+name = "fuel-core"
+owner = "FuelLabs"
+
+[repo.fuel-core.settings]
+crates = ["fuel-core-client", "fuel-core-types"]
+
+[repo.fuels-rs.details]
+name = "fuels-rs"
+owner = "FuelLabs"
+
+[repo.fuels-rs.settings]
+default-branch = "main"
+release-branches = ["release/v0.49"]
+clone-url = "git@github.com:FuelLabs/fuels-rs.git"
+
+[repo.fuels-rs.settings.handler]
+commit-author-email = "bot@fuel.sh"
+
+[repo.sway.details]
+name = "sway"
+owner = "FuelLabs"
+
+[repo.sway.settings]
+ecosystem = "forc"
+
+[repo.sway.settings.delivery]
+mode = "workflow-dispatch"
+workflow = "releasy.yml"
+"#;
+
+        let manifest_file = ManifestFile::try_from(manifest_str.to_string()).unwrap();
+        assert_eq!(manifest_file.warnings().count(), 0);
+        let manifest = manifest_file.manifest();
+
+        let fuels_rs = Repo::new("fuels-rs".to_string(), "FuelLabs".to_string());
+        let settings = manifest.settings(&fuels_rs);
+        assert_eq!(settings.default_branch(), Some("main"));
+        assert_eq!(
+            settings.release_branches().collect::<Vec<_>>(),
+            vec!["release/v0.49"]
+        );
+        assert_eq!(
+            settings.clone_url(),
+            Some("git@github.com:FuelLabs/fuels-rs.git")
+        );
+        assert_eq!(settings.ecosystem(), Ecosystem::Cargo);
+        let handler = settings.handler();
+        assert_eq!(handler.commit_author_name(), "fuel-bot");
+        assert_eq!(handler.commit_author_email(), "bot@fuel.sh");
+        assert!(!handler.force_push());
+        assert_eq!(handler.ci_trigger(), CiTrigger::RollingCommit);
+
+        let fuel_core = Repo::new("fuel-core".to_string(), "FuelLabs".to_string());
+        let settings = manifest.settings(&fuel_core);
+        assert_eq!(settings.default_branch(), Some("master"));
+        assert_eq!(
+            settings.crates().collect::<Vec<_>>(),
+            vec!["fuel-core-client", "fuel-core-types"]
+        );
+        assert_eq!(settings.handler().commit_author_email(), "releasy@fuel.sh");
+        assert_eq!(settings.delivery(), Delivery::RepositoryDispatch);
+
+        // Workflows are dispatched on the default branch unless a ref is declared.
+        let sway = Repo::new("sway".to_string(), "FuelLabs".to_string());
+        let settings = manifest.settings(&sway);
+        assert_eq!(settings.ecosystem(), Ecosystem::Forc);
+        assert_eq!(
+            settings.delivery(),
+            Delivery::WorkflowDispatch {
+                workflow: "releasy.yml".to_string(),
+                git_ref: Some("master".to_string())
+            }
+        );
+    }
+
+    #[test]
+    fn parse_manifest_file_custom_events() {
+        let manifest_str = r#"
+[current-repo]
+name = "fuels-rs"
+owner = "FuelLabs"
+
+[events.docs-published]
+action = "refresh-tracking-branch"
+
+[events.audit-requested]
+action = "hook"
+script = "./scripts/audit.sh"
+
+[repo.sway.details]
+name = "sway"
+owner = "FuelLabs"
+
+[repo.fuels-rs.details]
+name = "fuels-rs"
+owner = "FuelLabs"
+
+[repo.fuels-rs]
+dependencies = [{ name = "sway", events = ["new-commit-to-dependency", "docs-published"] }]
+"#;
+
+        let manifest_file = ManifestFile::try_from(manifest_str.to_string()).unwrap();
+        assert_eq!(manifest_file.warnings().count(), 0);
+        let manifest = manifest_file.manifest();
+
+        let docs_published = EventType::Custom("docs-published".to_string());
+        let audit_requested = EventType::Custom("audit-requested".to_string());
+        assert_eq!(
+            manifest.event_action(&docs_published),
+            Some(&EventAction::RefreshTrackingBranch)
+        );
+        assert_eq!(
+            manifest.event_action(&audit_requested),
+            Some(&EventAction::Hook {
+                script: "./scripts/audit.sh".to_string()
+            })
+        );
+        assert_eq!(
+            manifest.event_action(&EventType::Custom("unknown".to_string())),
+            None
+        );
+
+        let details = manifest.repo["fuels-rs"]
+            .dependencies()
+            .next()
+            .unwrap()
+            .details();
+        assert!(details.subscribes_to(&docs_published));
+        assert!(!details.subscribes_to(&audit_requested));
+
+        let invalid = manifest_str.replace("[events.audit-requested]", "[events.Audit_Requested]");
+        assert!(matches!(
+            ManifestFile::try_from(invalid),
+            Err(ManifestFileError::InvalidCustomEventName(name)) if name == "Audit_Requested"
+        ));
+    }
 }
@@ -1,12 +1,15 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet, VecDeque};
 
-use crate::{error::BuildPlanError, manifest::Manifest};
-use petgraph::{Directed, Direction};
-use releasy_core::repo::Repo;
+use crate::{
+    error::BuildPlanError,
+    manifest::{DependencyDetails, Manifest},
+};
+use petgraph::{visit::EdgeRef, Directed, Direction};
+use releasy_core::{default::DEFAULT_BRANCH_NAME, repo::Repo};
 
 type GraphIx = u32;
 type Node = Repo;
-type Edge = ();
+type Edge = DependencyDetails;
 type Graph = petgraph::stable_graph::StableGraph<Node, Edge, Directed, GraphIx>;
 
 type NodeIx = petgraph::prelude::NodeIndex;
@@ -16,16 +19,27 @@ type NodeIx = petgraph::prelude::NodeIndex;
 /// A node in the plan's graph represents a repository.
 ///
 /// An edge between `node a` to `node b` means `node b` depends on `node a`. So any event happening
-/// in the `node a` should be reported to `node b`.
+/// in the `node a` should be reported to `node b`, as long as the edge details allow it.
 pub struct Plan {
     graph: Graph,
     repo_to_node: HashMap<Repo, NodeIx>,
+    /// Default branches of the repos, which are the branches tracked along edges without a branch
+    /// mapping.
+    default_branches: HashMap<Repo, String>,
 }
 
 impl Plan {
     /// Try to generate a `BuildPlan` from a `Manifest`.
     pub fn try_from_manifest(manifest: Manifest) -> Result<Self, BuildPlanError> {
         let mut graph = Graph::new();
+        let default_branches = manifest
+            .repos()
+            .map(|repo| {
+                let settings = manifest.settings(repo);
+                let default_branch = settings.default_branch().unwrap_or(DEFAULT_BRANCH_NAME);
+                (repo.clone(), default_branch.to_string())
+            })
+            .collect::<HashMap<_, _>>();
         let repo_mapping = manifest.repo;
 
         // Create nodes, for each repo in the map create a node and it to the graph.
@@ -37,7 +51,7 @@ impl Plan {
         for (key, repo) in repo_mapping.iter() {
             let node_ix = graph.add_node(repo.details.clone());
             repo_to_node.insert(repo.details.clone(), node_ix);
-            key_to_node.insert(key, node_ix);
+            key_to_node.insert(key.as_str(), node_ix);
         }
 
         // Add edges between nodes with dependency information.
@@ -47,7 +61,8 @@ impl Plan {
                 .get(repo)
                 .expect("every repo should have a node in the graph!");
             // Collect node indices of dependencies for this repo.
-            for dependency_key in repo_entry.dependencies() {
+            for dependency in repo_entry.dependencies() {
+                let dependency_key = dependency.name();
                 let node_ix_of_dependency = key_to_node.get(dependency_key).ok_or_else(|| {
                     BuildPlanError::MissingProjectDefinition(
                         repo.name().to_string(),
@@ -55,13 +70,41 @@ impl Plan {
                     )
                 })?;
 
-                graph.add_edge(*node_ix_of_dependency, *node_ix_of_current_repo, ());
+                let details = dependency.details();
+                for pattern in details.paths() {
+                    glob::Pattern::new(pattern).map_err(|e| {
+                        BuildPlanError::InvalidPathPattern(
+                            repo.name().to_string(),
+                            pattern.to_string(),
+                            e,
+                        )
+                    })?;
+                }
+                let upstream = repo_mapping[dependency_key].details();
+                let tracked_branches = details
+                    .branch_mapping(upstream, &default_branches[upstream])
+                    .into_iter()
+                    .map(|(upstream_branch, _)| upstream_branch)
+                    .collect::<Vec<_>>();
+                if let Some(untracked) = details
+                    .based_upstream_branches()
+                    .find(|branch| !tracked_branches.contains(branch))
+                {
+                    return Err(BuildPlanError::UntrackedBaseBranch(
+                        repo.name().to_string(),
+                        dependency_key.to_string(),
+                        untracked.to_string(),
+                    ));
+                }
+
+                graph.add_edge(*node_ix_of_dependency, *node_ix_of_current_repo, details);
             }
         }
 
         Ok(Self {
             graph,
             repo_to_node,
+            default_branches,
         })
     }
 
@@ -101,12 +144,117 @@ impl Plan {
             .neighbors_directed(*node_ix, Direction::Incoming)
             .map(|neighbor_ix| &graph[neighbor_ix]))
     }
+
+    /// Returns the immediate repos which depends on the given repo, together with the details of
+    /// the edge connecting them.
+    pub fn downstream_edges(
+        &self,
+        repo: Repo,
+    ) -> Result<impl Iterator<Item = (&Repo, &Edge)> + '_, BuildPlanError> {
+        let node_ix = self
+            .repo_to_node
+            .get(&repo)
+            .ok_or(BuildPlanError::RepoNotFoundInGraph(repo))?;
+        let graph = self.graph();
+
+        Ok(graph
+            .edges_directed(*node_ix, Direction::Outgoing)
+            .map(|edge| (&graph[edge.target()], edge.weight())))
+    }
+
+    /// Returns the immediate repos which is depended by the given repo, together with the details
+    /// of the edge connecting them.
+    pub fn upstream_edges(
+        &self,
+        repo: Repo,
+    ) -> Result<impl Iterator<Item = (&Repo, &Edge)> + '_, BuildPlanError> {
+        let node_ix = self
+            .repo_to_node
+            .get(&repo)
+            .ok_or(BuildPlanError::RepoNotFoundInGraph(repo))?;
+        let graph = self.graph();
+
+        Ok(graph
+            .edges_directed(*node_ix, Direction::Incoming)
+            .map(|edge| (&graph[edge.source()], edge.weight())))
+    }
+
+    /// Returns every repo which directly or transitively depends on the given repo, in breadth-first
+    /// order, together with the details of the edge through which the repo is first reached.
+    ///
+    /// Only edges for which `follow` returns true are traversed. The given repo itself is never
+    /// included, even if it is part of a cycle.
+    pub fn downstream_closure(
+        &self,
+        repo: Repo,
+        follow: impl Fn(&Edge) -> bool,
+    ) -> Result<Vec<(&Repo, &Edge)>, BuildPlanError> {
+        let node_ix = *self
+            .repo_to_node
+            .get(&repo)
+            .ok_or(BuildPlanError::RepoNotFoundInGraph(repo))?;
+        let graph = self.graph();
+
+        let mut visited = HashSet::from([node_ix]);
+        let mut queue = VecDeque::from([node_ix]);
+        let mut closure = vec![];
+        while let Some(ix) = queue.pop_front() {
+            for edge in graph.edges_directed(ix, Direction::Outgoing) {
+                if follow(edge.weight()) && visited.insert(edge.target()) {
+                    closure.push((&graph[edge.target()], edge.weight()));
+                    queue.push_back(edge.target());
+                }
+            }
+        }
+        Ok(closure)
+    }
+
+    /// Returns the repos grouped in the order they should be released, every repo coming after
+    /// the repos it depends on.
+    ///
+    /// Repos depending on each other through a cycle cannot be ordered, they are grouped together
+    /// instead. Repos of a group are sorted by name.
+    pub fn release_order(&self) -> Vec<Vec<&Repo>> {
+        let graph = self.graph();
+        // Strongly connected components are found in reverse topological order.
+        petgraph::algo::tarjan_scc(graph)
+            .into_iter()
+            .rev()
+            .map(|component| {
+                let mut group = component
+                    .into_iter()
+                    .map(|node_ix| &graph[node_ix])
+                    .collect::<Vec<_>>();
+                group.sort();
+                group
+            })
+            .collect()
+    }
+
+    /// Returns the default branch declared in the settings of the repo, `master` if it is not
+    /// declared.
+    pub fn default_branch(&self, repo: &Repo) -> &str {
+        self.default_branches
+            .get(repo)
+            .map_or(DEFAULT_BRANCH_NAME, String::as_str)
+    }
+
+    /// Returns the details of the edge from `upstream` to `downstream`, if `downstream` depends on
+    /// `upstream`.
+    pub fn edge(&self, upstream: &Repo, downstream: &Repo) -> Option<&Edge> {
+        let upstream_ix = self.repo_to_node.get(upstream)?;
+        let downstream_ix = self.repo_to_node.get(downstream)?;
+        self.graph
+            .find_edge(*upstream_ix, *downstream_ix)
+            .map(|edge_ix| &self.graph[edge_ix])
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::{Plan, Repo};
-    use crate::manifest::ManifestFile;
+    use crate::{error::BuildPlanError, manifest::ManifestFile};
+    use releasy_core::event::EventType;
 
     #[test]
     fn generate_plan_with_two_projects() {
@@ -259,4 +407,219 @@ dependencies = ["rust-sdk"]
         let expected_forc_wallet_neighbors = vec![sway_repo];
         assert_eq!(forc_wallet_neighbors, expected_forc_wallet_neighbors)
     }
+
+    #[test]
+    fn test_edge_query_with_details() {
+        let manifest_str = r#"
+[current-repo]
+name = "fuels-rs"
+owner = "FuelLabs"
+
+[repo.sway.details]
+name = "sway"
+owner = "FuelLabs"
+
+[repo.fuel-core.details]
+name = "fuel-core"
+owner = "FuelLabs"
+
+[repo.fuels-rs.details]
+name = "fuels-rs"
+owner = "FuelLabs"
+
+[repo.fuels-rs]
+dependencies = ["fuel-core", { name = "sway", events = ["new-release"] }]
+"#;
+        let manifest = ManifestFile::try_from(manifest_str.to_string())
+            .unwrap()
+            .manifest();
+        let plan = Plan::try_from_manifest(manifest).unwrap();
+
+        let sway_repo = Repo::new("sway".to_string(), "FuelLabs".to_string());
+        let fuels_rs_repo = Repo::new("fuels-rs".to_string(), "FuelLabs".to_string());
+
+        let sway_edges: Vec<_> = plan.downstream_edges(sway_repo.clone()).unwrap().collect();
+        assert_eq!(sway_edges.len(), 1);
+        assert_eq!(sway_edges[0].0, &fuels_rs_repo);
+        assert!(sway_edges[0].1.subscribes_to(&EventType::NewRelease));
+        assert!(!sway_edges[0]
+            .1
+            .subscribes_to(&EventType::NewCommitToDependency));
+
+        let upstream_names: Vec<_> = plan
+            .upstream_edges(fuels_rs_repo.clone())
+            .unwrap()
+            .map(|(repo, edge)| (repo.name(), edge.name()))
+            .collect();
+        assert_eq!(
+            upstream_names,
+            vec![("sway", "sway"), ("fuel-core", "fuel-core")]
+        );
+
+        assert!(plan.edge(&sway_repo, &fuels_rs_repo).is_some());
+        assert!(plan.edge(&fuels_rs_repo, &sway_repo).is_none());
+    }
+
+    #[test]
+    fn reject_invalid_path_pattern() {
+        let manifest_str = r#"
+[current-repo]
+name = "fuels-rs"
+owner = "FuelLabs"
+
+[repo.sway.details]
+name = "sway"
+owner = "FuelLabs"
+
+[repo.fuels-rs.details]
+name = "fuels-rs"
+owner = "FuelLabs"
+
+[repo.fuels-rs]
+dependencies = [{ name = "sway", paths = ["sway-lib-std/***"] }]
+"#;
+        let manifest = ManifestFile::try_from(manifest_str.to_string())
+            .unwrap()
+            .manifest();
+        assert!(Plan::try_from_manifest(manifest).is_err());
+    }
+
+    #[test]
+    fn reject_base_branch_of_untracked_branch() {
+        let manifest_str = r#"
+[current-repo]
+name = "fuels-rs"
+owner = "FuelLabs"
+
+[repo.sway.details]
+name = "sway"
+owner = "FuelLabs"
+
+[repo.fuels-rs.details]
+name = "fuels-rs"
+owner = "FuelLabs"
+
+[repo.fuels-rs]
+dependencies = [{ name = "sway", base-branches = { "release/v0.46" = "release/v0.20" } }]
+"#;
+        let manifest = ManifestFile::try_from(manifest_str.to_string())
+            .unwrap()
+            .manifest();
+        assert!(matches!(
+            Plan::try_from_manifest(manifest),
+            Err(BuildPlanError::UntrackedBaseBranch(..))
+        ));
+    }
+
+    #[test]
+    fn release_order_with_cycle() {
+        let manifest_str = r#"
+[current-repo]
+name = "sway"
+owner = "FuelLabs"
+
+[repo.fuel-core.details]
+name = "fuel-core"
+owner = "FuelLabs"
+
+[repo.fuels-rs.details]
+name = "fuels-rs"
+owner = "FuelLabs"
+
+[repo.fuels-rs]
+dependencies = ["fuel-core", "sway"]
+
+[repo.sway.details]
+name = "sway"
+owner = "FuelLabs"
+
+[repo.sway]
+dependencies = ["fuels-rs"]
+
+[repo.forc-wallet.details]
+name = "forc-wallet"
+owner = "FuelLabs"
+
+[repo.forc-wallet]
+dependencies = ["fuels-rs"]
+"#;
+        let manifest = ManifestFile::try_from(manifest_str.to_string())
+            .unwrap()
+            .manifest();
+        let plan = Plan::try_from_manifest(manifest).unwrap();
+
+        let order = plan
+            .release_order()
+            .into_iter()
+            .map(|group| group.into_iter().map(Repo::name).collect::<Vec<_>>())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            order,
+            vec![
+                vec!["fuel-core"],
+                vec!["fuels-rs", "sway"],
+                vec!["forc-wallet"]
+            ]
+        );
+    }
+
+    #[test]
+    fn test_downstream_closure_with_cycle() {
+        let manifest_str = r#"
+[current-repo]
+name = "fuel-core"
+owner = "FuelLabs"
+
+[repo.fuel-core.details]
+name = "fuel-core"
+owner = "FuelLabs"
+
+[repo.fuels-rs.details]
+name = "fuels-rs"
+owner = "FuelLabs"
+
+[repo.fuels-rs]
+dependencies = ["fuel-core", "sway"]
+
+[repo.sway.details]
+name = "sway"
+owner = "FuelLabs"
+
+[repo.sway]
+dependencies = ["fuel-core", "fuels-rs"]
+
+[repo.forc-wallet.details]
+name = "forc-wallet"
+owner = "FuelLabs"
+
+[repo.forc-wallet]
+dependencies = [{ name = "fuels-rs", events = ["new-release"] }]
+"#;
+        let manifest = ManifestFile::try_from(manifest_str.to_string())
+            .unwrap()
+            .manifest();
+        let plan = Plan::try_from_manifest(manifest).unwrap();
+        let fuel_core_repo = Repo::new("fuel-core".to_string(), "FuelLabs".to_string());
+
+        let closure: Vec<_> = plan
+            .downstream_closure(fuel_core_repo.clone(), |_| true)
+            .unwrap()
+            .into_iter()
+            .map(|(repo, _)| repo.name())
+            .collect();
+        // `forc-wallet` is only reachable through `fuels-rs`, so it comes last.
+        assert_eq!(closure.len(), 3);
+        assert_eq!(closure[2], "forc-wallet");
+
+        let closure: Vec<_> = plan
+            .downstream_closure(fuel_core_repo, |edge| {
+                edge.subscribes_to(&EventType::NewCommitToDependency)
+            })
+            .unwrap()
+            .into_iter()
+            .map(|(repo, _)| repo.name())
+            .collect();
+        assert_eq!(closure.len(), 2);
+        assert!(!closure.contains(&"forc-wallet"));
+    }
 }
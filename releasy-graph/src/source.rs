@@ -0,0 +1,287 @@
+//! Resolution of shared plan files extended by a manifest.
+use std::{
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use toml::{Table, Value};
+
+use crate::error::ManifestFileError;
+
+/// Key of the table describing the plan file extended by a manifest.
+pub(crate) const EXTENDS_KEY: &str = "extends";
+/// Plan files extending each other deeper than this are rejected, which also catches cycles.
+const MAX_EXTENDS_DEPTH: usize = 8;
+/// Name of the plan file looked up in plan repos if none is specified.
+const DEFAULT_PLAN_FILE_NAME: &str = "repo-plan.toml";
+/// Git ref fetched from plan repos if none is specified.
+const DEFAULT_PLAN_REV: &str = "HEAD";
+
+/// Location of a plan file extended by a manifest, either a local path:
+///
+/// ```toml
+/// [extends]
+/// path = "../releasy-plan/repo-plan.toml"
+/// ```
+///
+/// or a file in a git repo pinned to a ref:
+///
+/// ```toml
+/// [extends]
+/// git = "https://github.com/FuelLabs/releasy-plan"
+/// rev = "v1"
+/// file = "repo-plan.toml"
+/// ```
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub struct PlanSource {
+    /// Path of the plan file, relative to the manifest extending it.
+    path: Option<PathBuf>,
+    /// URL of the git repo containing the plan file.
+    git: Option<String>,
+    /// Branch, tag or commit of the plan repo, `HEAD` if omitted.
+    rev: Option<String>,
+    /// Path of the plan file in the plan repo, `repo-plan.toml` if omitted.
+    file: Option<PathBuf>,
+}
+
+impl PlanSource {
+    /// Returns the local path of the plan file, fetching the plan repo into the cache if needed.
+    ///
+    /// Relative paths are resolved against `manifest_dir`. A warning is pushed to `warnings` if a
+    /// stale cached checkout is used because the plan repo could not be fetched.
+    pub fn fetch(
+        &self,
+        manifest_dir: &Path,
+        warnings: &mut Vec<String>,
+    ) -> Result<PathBuf, ManifestFileError> {
+        match (&self.path, &self.git) {
+            (Some(path), None) => Ok(manifest_dir.join(path)),
+            (None, Some(git)) => {
+                let rev = self.rev.as_deref().unwrap_or(DEFAULT_PLAN_REV);
+                let checkout = fetch_plan_repo(&cache_dir().join("plans"), git, rev, warnings)?;
+                let file = self
+                    .file
+                    .clone()
+                    .unwrap_or_else(|| PathBuf::from(DEFAULT_PLAN_FILE_NAME));
+                Ok(checkout.join(file))
+            }
+            _ => Err(ManifestFileError::InvalidPlanSource(
+                "exactly one of `path` or `git` should be specified".to_string(),
+            )),
+        }
+    }
+}
+
+/// Returns the directory used for caching plan repos.
+///
+/// `RELEASY_CACHE_DIR` takes precedence, then `XDG_CACHE_HOME` and `HOME` are tried.
+pub fn cache_dir() -> PathBuf {
+    if let Ok(dir) = std::env::var("RELEASY_CACHE_DIR") {
+        return PathBuf::from(dir);
+    }
+    let base = std::env::var("XDG_CACHE_HOME")
+        .map(PathBuf::from)
+        .or_else(|_| std::env::var("HOME").map(|home| PathBuf::from(home).join(".cache")))
+        .unwrap_or_else(|_| std::env::temp_dir());
+    base.join("releasy")
+}
+
+/// Replaces the `extends` table of the given manifest with the contents of the plan it extends.
+///
+/// Tables are merged recursively, with the values declared in the manifest overriding the ones
+/// in the extended plan. Any other value, including arrays, replaces the extended one.
+pub(crate) fn resolve_extends(
+    table: Table,
+    manifest_dir: &Path,
+    warnings: &mut Vec<String>,
+) -> Result<Table, ManifestFileError> {
+    resolve_extends_with_depth(table, manifest_dir, warnings, 0)
+}
+
+fn resolve_extends_with_depth(
+    mut table: Table,
+    manifest_dir: &Path,
+    warnings: &mut Vec<String>,
+    depth: usize,
+) -> Result<Table, ManifestFileError> {
+    let Some(extends) = table.remove(EXTENDS_KEY) else {
+        return Ok(table);
+    };
+    if depth >= MAX_EXTENDS_DEPTH {
+        return Err(ManifestFileError::ExtendsTooDeep(MAX_EXTENDS_DEPTH));
+    }
+    let source = PlanSource::deserialize(extends)
+        .map_err(|e| ManifestFileError::InvalidPlanSource(e.to_string()))?;
+    let base_path = source.fetch(manifest_dir, warnings)?;
+    let base_str = std::fs::read_to_string(&base_path)
+        .map_err(|e| ManifestFileError::MissingManifestFile(format!("{base_path:?}"), e))?;
+    let base_table = base_str
+        .parse::<Table>()
+        .map_err(ManifestFileError::FailedToParseManifest)?;
+    let base_dir = base_path.parent().unwrap_or(manifest_dir);
+    let base_table = resolve_extends_with_depth(base_table, base_dir, warnings, depth + 1)?;
+    Ok(merge_tables(base_table, table))
+}
+
+/// Merges `overlay` into `base`, recursing into tables present in both.
+fn merge_tables(mut base: Table, overlay: Table) -> Table {
+    for (key, value) in overlay {
+        let merged = match (base.remove(&key), value) {
+            (Some(Value::Table(base_value)), Value::Table(overlay_value)) => {
+                Value::Table(merge_tables(base_value, overlay_value))
+            }
+            (_, value) => value,
+        };
+        base.insert(key, merged);
+    }
+    base
+}
+
+/// Fetches the given ref of a plan repo into `plans_dir` and returns the path of the checkout.
+///
+/// Checkouts of full commit hashes never change, so they are reused as is. Other refs are fetched
+/// again, falling back to the cached checkout with a warning if the fetch fails (e.g. while
+/// offline).
+fn fetch_plan_repo(
+    plans_dir: &Path,
+    git: &str,
+    rev: &str,
+    warnings: &mut Vec<String>,
+) -> Result<PathBuf, ManifestFileError> {
+    // Hashed so that distinct sources never share a checkout.
+    let cache_key = Sha256::digest(format!("{git}@{rev}"))
+        .iter()
+        .map(|byte| format!("{byte:02x}"))
+        .collect::<String>();
+    let checkout = plans_dir.join(cache_key);
+    let is_commit_hash = rev.len() == 40 && rev.chars().all(|c| c.is_ascii_hexdigit());
+    if checkout.join(".git").exists() && is_commit_hash {
+        return Ok(checkout);
+    }
+
+    let fetched = std::fs::create_dir_all(&checkout)
+        .map_err(|e| e.to_string())
+        .and_then(|_| run_git(&["init", "--quiet"], &checkout))
+        .and_then(|_| run_git(&["fetch", "--quiet", "--depth", "1", git, rev], &checkout))
+        .and_then(|_| run_git(&["checkout", "--quiet", "--force", "FETCH_HEAD"], &checkout));
+    match fetched {
+        Ok(_) => Ok(checkout),
+        Err(reason) => match run_git(&["rev-parse", "--verify", "--quiet", "HEAD"], &checkout) {
+            Ok(cached_commit) => {
+                warnings.push(format!(
+                    "  WARNING! cannot fetch `{rev}` of {git} ({reason}), using the cached plan at {cached_commit}"
+                ));
+                Ok(checkout)
+            }
+            Err(_) => Err(ManifestFileError::FailedToFetchPlan(
+                format!("{git}@{rev}"),
+                reason,
+            )),
+        },
+    }
+}
+
+/// Runs git in `dir`, returning its trimmed output.
+fn run_git(args: &[&str], dir: &Path) -> Result<String, String> {
+    let output = Command::new("git")
+        .args(args)
+        .current_dir(dir)
+        .output()
+        .map_err(|e| e.to_string())?;
+    if output.status.success() {
+        Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+    } else {
+        Err(String::from_utf8_lossy(&output.stderr).trim().to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{fetch_plan_repo, run_git};
+    use crate::manifest::ManifestFile;
+    use releasy_core::repo::Repo;
+    use std::path::PathBuf;
+
+    const EXTENDING_TEST_MANIFEST_FILE_NAME: &str = "repo-plan-extending.toml";
+
+    #[test]
+    fn parse_manifest_extending_local_plan() {
+        let test_manifest_file = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+            .join("tests")
+            .join(EXTENDING_TEST_MANIFEST_FILE_NAME);
+
+        let manifest_file = ManifestFile::from_file(&test_manifest_file).unwrap();
+        assert_eq!(manifest_file.warnings().count(), 0);
+        let manifest = manifest_file.manifest();
+        assert_eq!(manifest.current_repo().name(), "fuels-rs");
+
+        // Settings declared in the extending manifest override the shared ones, while the rest of
+        // the shared plan is kept as is.
+        let fuels_rs = Repo::new("fuels-rs".to_string(), "FuelLabs".to_string());
+        let settings = manifest.settings(&fuels_rs);
+        assert_eq!(settings.default_branch(), Some("main"));
+        assert_eq!(settings.handler().commit_author_name(), "fuel-bot");
+        let dependencies = manifest
+            .repo_entry(&fuels_rs)
+            .unwrap()
+            .dependencies()
+            .map(|dependency| dependency.name())
+            .collect::<Vec<_>>();
+        assert_eq!(dependencies, vec!["fuel-core"]);
+    }
+
+    #[test]
+    fn reject_ambiguous_plan_source() {
+        let manifest_str = r#"
+[extends]
+path = "repo-plan.toml"
+git = "https://github.com/FuelLabs/releasy-plan"
+
+[current-repo]
+name = "fuels-rs"
+owner = "FuelLabs"
+"#;
+        assert!(ManifestFile::try_from(manifest_str.to_string()).is_err());
+    }
+
+    #[test]
+    fn warn_when_using_stale_plan_checkout() {
+        let dir = std::env::temp_dir().join(format!("releasy-plan-cache-{}", std::process::id()));
+        let plan_repo = dir.join("releasy-plan");
+        let plans_dir = dir.join("plans");
+        std::fs::create_dir_all(&plan_repo).unwrap();
+        let git = |args: &[&str]| run_git(args, &plan_repo).unwrap();
+        git(&["init", "--quiet"]);
+        std::fs::write(plan_repo.join("repo-plan.toml"), "").unwrap();
+        git(&["add", "repo-plan.toml"]);
+        git(&[
+            "-c",
+            "user.name=releasy",
+            "-c",
+            "user.email=releasy@fuel.sh",
+            "commit",
+            "--quiet",
+            "-m",
+            "Add plan",
+        ]);
+        let commit = git(&["rev-parse", "HEAD"]);
+        let url = plan_repo.to_str().unwrap();
+
+        let mut warnings = vec![];
+        let checkout = fetch_plan_repo(&plans_dir, url, "HEAD", &mut warnings).unwrap();
+        assert!(warnings.is_empty());
+        assert!(checkout.join("repo-plan.toml").exists());
+
+        std::fs::remove_dir_all(&plan_repo).unwrap();
+        let cached = fetch_plan_repo(&plans_dir, url, "HEAD", &mut warnings).unwrap();
+        assert_eq!(cached, checkout);
+        assert_eq!(warnings.len(), 1);
+        assert!(warnings[0].contains("`HEAD`"), "{}", warnings[0]);
+        assert!(warnings[0].contains(&commit), "{}", warnings[0]);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,712 @@
+//! Changelogs aggregated across the dependency graph, computed from local checkouts.
+//!
+//! The changelog of a repo between two revisions lists its commits, grouped by their conventional
+//! commit type, and the upstream repos of the plan whose locked revision changed in between. The
+//! changelog of each bumped upstream repo is then collected for the range between its two locked
+//! revisions, and so on up the graph.
+use std::{
+    collections::{BTreeMap, HashMap, HashSet, VecDeque},
+    path::Path,
+};
+
+use releasy_core::repo::Repo;
+use releasy_graph::{infer::Checkout, manifest::Manifest, plan::Plan};
+use serde::Serialize;
+use toml::{Table, Value};
+
+use crate::handle::{find_repo, git_output};
+
+/// Lock files upstream revisions are read from.
+const LOCK_FILE_NAMES: [&str; 2] = ["Cargo.lock", "Forc.lock"];
+
+/// Footers marking a commit as breaking in conventional commit messages.
+const BREAKING_CHANGE_FOOTERS: [&str; 2] = ["BREAKING CHANGE:", "BREAKING-CHANGE:"];
+
+/// Kind of a change, taken from the type of its conventional commit message.
+#[derive(Serialize, Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "kebab-case")]
+pub enum ChangeKind {
+    Feature,
+    Fix,
+    Performance,
+    /// Any other type, or a commit message not following the conventional commit format.
+    Other,
+}
+
+impl ChangeKind {
+    const ALL: [ChangeKind; 4] = [
+        ChangeKind::Feature,
+        ChangeKind::Fix,
+        ChangeKind::Performance,
+        ChangeKind::Other,
+    ];
+
+    fn from_commit_type(commit_type: &str) -> Self {
+        match commit_type.to_ascii_lowercase().as_str() {
+            "feat" | "feature" => ChangeKind::Feature,
+            "fix" | "bugfix" => ChangeKind::Fix,
+            "perf" => ChangeKind::Performance,
+            _ => ChangeKind::Other,
+        }
+    }
+
+    fn heading(&self) -> &'static str {
+        match self {
+            ChangeKind::Feature => "Features",
+            ChangeKind::Fix => "Bug Fixes",
+            ChangeKind::Performance => "Performance",
+            ChangeKind::Other => "Other Changes",
+        }
+    }
+}
+
+/// A commit listed in a changelog.
+#[derive(Serialize, Clone, Debug, PartialEq, Eq)]
+pub struct Change {
+    commit: String,
+    kind: ChangeKind,
+    scope: Option<String>,
+    /// Whether the commit is marked as breaking with `!` or a `BREAKING CHANGE` footer.
+    breaking: bool,
+    description: String,
+}
+
+impl Change {
+    /// Parses the message of a commit, as `<type>(<scope>)!: <description>` if it follows the
+    /// conventional commit format.
+    fn parse(commit: &str, subject: &str, body: &str) -> Self {
+        let breaking_footer = body
+            .lines()
+            .any(|line| BREAKING_CHANGE_FOOTERS.iter().any(|f| line.starts_with(f)));
+        let conventional = subject.split_once(':').and_then(|(prefix, description)| {
+            let (prefix, breaking) = match prefix.strip_suffix('!') {
+                Some(prefix) => (prefix, true),
+                None => (prefix, false),
+            };
+            let (commit_type, scope) = match prefix.split_once('(') {
+                Some((commit_type, scope)) => (commit_type, Some(scope.strip_suffix(')')?)),
+                None => (prefix, None),
+            };
+            let valid = !commit_type.is_empty()
+                && commit_type.chars().all(|c| c.is_ascii_alphanumeric())
+                && !description.trim().is_empty();
+            valid.then(|| Change {
+                commit: commit.to_string(),
+                kind: ChangeKind::from_commit_type(commit_type),
+                scope: scope.map(str::to_string),
+                breaking: breaking || breaking_footer,
+                description: description.trim().to_string(),
+            })
+        });
+        conventional.unwrap_or_else(|| Change {
+            commit: commit.to_string(),
+            kind: ChangeKind::Other,
+            scope: None,
+            breaking: breaking_footer,
+            description: subject.trim().to_string(),
+        })
+    }
+
+    pub fn commit(&self) -> &str {
+        &self.commit
+    }
+
+    pub fn kind(&self) -> ChangeKind {
+        self.kind
+    }
+
+    pub fn is_breaking(&self) -> bool {
+        self.breaking
+    }
+
+    fn to_markdown(&self) -> String {
+        let short_commit = &self.commit[..self.commit.len().min(7)];
+        match &self.scope {
+            Some(scope) => format!("- **{scope}:** {} ({short_commit})", self.description),
+            None => format!("- {} ({short_commit})", self.description),
+        }
+    }
+}
+
+/// Revision of an upstream repo locked by a downstream repo, as found in its lock files.
+#[derive(Serialize, Clone, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct LockedRevision {
+    version: Option<String>,
+    /// Tag of the git source, e.g. `v0.46.0` of `git+https://github.com/fuellabs/sway?tag=v0.46.0`.
+    tag: Option<String>,
+    /// Commit of the git source.
+    commit: Option<String>,
+}
+
+impl LockedRevision {
+    /// Reads the revision of a `[[package]]` entry of a `Cargo.lock` or a `Forc.lock`. Packages
+    /// sourced from a path are not locked to a revision.
+    fn from_lock_package(package: &Table) -> Option<Self> {
+        let source = package.get("source").and_then(Value::as_str);
+        if source.is_some_and(|source| source.starts_with("path+")) {
+            return None;
+        }
+        let git_source = source.and_then(|source| source.strip_prefix("git+"));
+        let (tag, commit) = match git_source {
+            Some(git_source) => {
+                let (url, commit) = match git_source.split_once('#') {
+                    Some((url, commit)) => (url, Some(commit.to_string())),
+                    None => (git_source, None),
+                };
+                let tag = url
+                    .split_once('?')
+                    .into_iter()
+                    .flat_map(|(_, query)| query.split('&'))
+                    .find_map(|pair| pair.strip_prefix("tag="))
+                    .map(str::to_string);
+                (tag, commit)
+            }
+            None => (None, None),
+        };
+        let revision = Self {
+            version: package
+                .get("version")
+                .and_then(Value::as_str)
+                .map(str::to_string),
+            tag,
+            commit,
+        };
+        (revision != Self::default()).then_some(revision)
+    }
+
+    /// Returns a human readable label of the revision, its version if it is known.
+    pub fn label(&self) -> String {
+        self.version
+            .clone()
+            .or_else(|| self.tag.clone())
+            .or_else(|| {
+                self.commit
+                    .as_ref()
+                    .map(|commit| commit[..commit.len().min(7)].to_string())
+            })
+            .unwrap_or_default()
+    }
+
+    /// Returns the revisions of the upstream checkout this revision may be found at, the most
+    /// precise first.
+    fn candidates(&self) -> Vec<String> {
+        let mut candidates = vec![];
+        candidates.extend(self.tag.clone());
+        candidates.extend(self.commit.clone());
+        if let Some(version) = &self.version {
+            candidates.push(format!("v{version}"));
+            candidates.push(version.clone());
+        }
+        candidates
+    }
+
+    /// Returns the first candidate revision present in the upstream checkout.
+    fn resolve(&self, repo_path: &Path) -> Option<String> {
+        self.candidates().into_iter().find(|candidate| {
+            git_output(
+                &[
+                    "rev-parse",
+                    "--verify",
+                    "--quiet",
+                    &format!("{candidate}^{{commit}}"),
+                ],
+                repo_path,
+            )
+            .is_ok()
+        })
+    }
+}
+
+/// An upstream repo whose locked revision changed between the two revisions of a changelog.
+#[derive(Serialize, Clone, Debug, PartialEq, Eq)]
+pub struct UpstreamBump {
+    repo: Repo,
+    /// Locked revision at the start of the range, `None` if the upstream repo was not depended on.
+    from: Option<LockedRevision>,
+    /// Locked revision at the end of the range, `None` if the upstream repo is no longer depended
+    /// on.
+    to: Option<LockedRevision>,
+}
+
+impl UpstreamBump {
+    pub fn repo(&self) -> &Repo {
+        &self.repo
+    }
+
+    fn to_markdown(&self) -> String {
+        let repo = format!("{}/{}", self.repo.owner(), self.repo.name());
+        match (&self.from, &self.to) {
+            (Some(from), Some(to)) => format!("- {repo}: {} → {}", from.label(), to.label()),
+            (None, Some(to)) => format!("- {repo}: added at {}", to.label()),
+            (Some(from), None) => format!("- {repo}: removed, was at {}", from.label()),
+            (None, None) => format!("- {repo}"),
+        }
+    }
+}
+
+/// Changes of a single repo between two of its revisions.
+#[derive(Serialize, Clone, Debug, PartialEq, Eq)]
+pub struct RepoChangelog {
+    repo: Repo,
+    from: String,
+    to: String,
+    bumps: Vec<UpstreamBump>,
+    changes: Vec<Change>,
+}
+
+impl RepoChangelog {
+    pub fn repo(&self) -> &Repo {
+        &self.repo
+    }
+
+    pub fn bumps(&self) -> impl Iterator<Item = &UpstreamBump> {
+        self.bumps.iter()
+    }
+
+    pub fn changes(&self) -> impl Iterator<Item = &Change> {
+        self.changes.iter()
+    }
+
+    fn title(&self) -> String {
+        format!(
+            "{}/{} {}...{}",
+            self.repo.owner(),
+            self.repo.name(),
+            self.from,
+            self.to
+        )
+    }
+
+    fn to_markdown(&self) -> Vec<String> {
+        let mut lines = vec![format!("## {}", self.title())];
+        if self.bumps.is_empty() && self.changes.is_empty() {
+            lines.extend([String::new(), "No changes.".to_string()]);
+        }
+        let mut section = |heading: &str, items: Vec<String>| {
+            if !items.is_empty() {
+                lines.extend([String::new(), format!("### {heading}"), String::new()]);
+                lines.extend(items);
+            }
+        };
+        section(
+            "Upstream Bumps",
+            self.bumps.iter().map(UpstreamBump::to_markdown).collect(),
+        );
+        section(
+            "Breaking Changes",
+            self.changes
+                .iter()
+                .filter(|change| change.breaking)
+                .map(Change::to_markdown)
+                .collect(),
+        );
+        for kind in ChangeKind::ALL {
+            section(
+                kind.heading(),
+                self.changes
+                    .iter()
+                    .filter(|change| !change.breaking && change.kind == kind)
+                    .map(Change::to_markdown)
+                    .collect(),
+            );
+        }
+        lines
+    }
+}
+
+/// Changelog of a repo, followed by the changelogs of the upstream repos bumped in between.
+#[derive(Serialize, Clone, Debug, Default, PartialEq, Eq)]
+pub struct Changelog {
+    repos: Vec<RepoChangelog>,
+    /// Upstream repos that were bumped but whose changes could not be collected.
+    warnings: Vec<String>,
+}
+
+impl Changelog {
+    pub fn repos(&self) -> impl Iterator<Item = &RepoChangelog> {
+        self.repos.iter()
+    }
+
+    pub fn warnings(&self) -> impl Iterator<Item = &String> {
+        self.warnings.iter()
+    }
+
+    /// Formats the changelog as Markdown, e.g. for release notes.
+    pub fn to_markdown(&self) -> String {
+        let mut lines = vec![];
+        if let Some(root) = self.repos.first() {
+            lines.extend([format!("# Changelog of {}", root.title()), String::new()]);
+        }
+        for (ix, repo) in self.repos.iter().enumerate() {
+            if ix > 0 {
+                lines.push(String::new());
+            }
+            lines.extend(repo.to_markdown());
+        }
+        lines.join("\n")
+    }
+}
+
+/// Collects the changelog of the given repo between two of its revisions, usually release tags,
+/// together with the changelogs of the upstream repos bumped in between.
+///
+/// The checkout of a repo is expected to be at `<checkouts_dir>/<repo name>`, with the tags of its
+/// releases fetched. Upstream revisions are read from the `Cargo.lock` and `Forc.lock` files of
+/// the downstream checkout, at both ends of the range, and looked up in the upstream checkout by
+/// their git tag or commit, or by the `v<version>` and `<version>` tags.
+pub fn changelog(
+    manifest: &Manifest,
+    repo: &str,
+    from: &str,
+    to: &str,
+    checkouts_dir: &Path,
+) -> anyhow::Result<Changelog> {
+    let plan = Plan::try_from_manifest(manifest.clone())?;
+    let repo = find_repo(manifest, repo)?;
+    let mut packages = HashMap::new();
+    let mut changelog = Changelog::default();
+    let mut visited = HashSet::new();
+    let mut queue = VecDeque::from([(repo.clone(), from.to_string(), to.to_string())]);
+    while let Some((repo, from, to)) = queue.pop_front() {
+        if !visited.insert((repo.clone(), from.clone(), to.clone())) {
+            continue;
+        }
+        let repo_path = checkouts_dir.join(repo.name());
+        if !repo_path.is_dir() {
+            anyhow::bail!(
+                "no local checkout of {} found at {repo_path:?}",
+                repo.name()
+            );
+        }
+        let locked_from = locked_revisions(&from, &repo_path)?;
+        let locked_to = locked_revisions(&to, &repo_path)?;
+        let mut bumps = vec![];
+        for upstream in plan.upstream_repos(repo.clone())? {
+            let upstream_path = checkouts_dir.join(upstream.name());
+            if !packages.contains_key(upstream) {
+                packages.insert(
+                    upstream.clone(),
+                    upstream_packages(manifest, upstream, &upstream_path)?,
+                );
+            }
+            let upstream_packages = &packages[upstream];
+            let bump = UpstreamBump {
+                repo: upstream.clone(),
+                from: locked_revision(&locked_from, upstream_packages),
+                to: locked_revision(&locked_to, upstream_packages),
+            };
+            if bump.from == bump.to {
+                continue;
+            }
+            if let (Some(bump_from), Some(bump_to)) = (&bump.from, &bump.to) {
+                let range = upstream_path
+                    .is_dir()
+                    .then(|| {
+                        let from = bump_from.resolve(&upstream_path)?;
+                        let to = bump_to.resolve(&upstream_path)?;
+                        Some((from, to))
+                    })
+                    .flatten();
+                match range {
+                    Some((from, to)) => queue.push_back((upstream.clone(), from, to)),
+                    None => changelog.warnings.push(format!(
+                        "changes of {}/{} between {} and {} are not collected, as they are not \
+                         found in {upstream_path:?}",
+                        upstream.owner(),
+                        upstream.name(),
+                        bump_from.label(),
+                        bump_to.label()
+                    )),
+                }
+            }
+            bumps.push(bump);
+        }
+        changelog.repos.push(RepoChangelog {
+            changes: changes(&from, &to, &repo_path)?,
+            repo,
+            from,
+            to,
+            bumps,
+        });
+    }
+    Ok(changelog)
+}
+
+/// Returns the names of the packages published from the upstream repo, from its `crates` settings
+/// and from the manifests of its checkout.
+fn upstream_packages(
+    manifest: &Manifest,
+    upstream: &Repo,
+    upstream_path: &Path,
+) -> anyhow::Result<HashSet<String>> {
+    let mut packages = manifest
+        .settings(upstream)
+        .crates()
+        .cloned()
+        .collect::<HashSet<_>>();
+    if upstream_path.is_dir() {
+        packages.extend(Checkout::scan(upstream_path)?.packages().cloned());
+    }
+    Ok(packages)
+}
+
+/// Returns the revisions locked for each package in the lock files of the checkout at `rev`.
+fn locked_revisions(rev: &str, repo_path: &Path) -> anyhow::Result<Vec<(String, LockedRevision)>> {
+    let files = git_output(&["ls-tree", "-r", "--name-only", rev], repo_path)?;
+    let lock_files = files.lines().filter(|file| {
+        let file_name = file.rsplit('/').next().unwrap_or(file);
+        LOCK_FILE_NAMES.contains(&file_name)
+    });
+    let mut locked = vec![];
+    for lock_file in lock_files {
+        let contents = git_output(&["show", &format!("{rev}:{lock_file}")], repo_path)?;
+        let table = contents
+            .parse::<Table>()
+            .map_err(|e| anyhow::anyhow!("cannot parse {lock_file} at {rev}: {e}"))?;
+        locked.extend(lock_packages(&table));
+    }
+    Ok(locked)
+}
+
+/// Returns the names and revisions of the `[[package]]` entries of a lock file.
+fn lock_packages(table: &Table) -> Vec<(String, LockedRevision)> {
+    let packages = table.get("package").and_then(Value::as_array);
+    packages
+        .into_iter()
+        .flatten()
+        .filter_map(Value::as_table)
+        .filter_map(|package| {
+            let name = package.get("name").and_then(Value::as_str)?;
+            let revision = LockedRevision::from_lock_package(package)?;
+            Some((name.to_string(), revision))
+        })
+        .collect()
+}
+
+/// Returns the revision of the upstream repo locked by most of its packages, the highest version
+/// winning ties, as packages versioned independently may be locked as well.
+fn locked_revision(
+    locked: &[(String, LockedRevision)],
+    upstream_packages: &HashSet<String>,
+) -> Option<LockedRevision> {
+    let mut counts: BTreeMap<&LockedRevision, usize> = BTreeMap::new();
+    for (_, revision) in locked
+        .iter()
+        .filter(|(name, _)| upstream_packages.contains(name))
+    {
+        *counts.entry(revision).or_default() += 1;
+    }
+    counts
+        .into_iter()
+        .max_by_key(|(revision, count)| (*count, version_key(revision.version.as_deref())))
+        .map(|(revision, _)| revision.clone())
+}
+
+/// Returns the numeric components of a version, for comparing versions without a semver parser.
+fn version_key(version: Option<&str>) -> Vec<u64> {
+    version
+        .into_iter()
+        .flat_map(|version| version.split(['.', '-', '+']))
+        .map_while(|component| component.parse().ok())
+        .collect()
+}
+
+/// Returns the commits of the checkout in `from..to`, newest first, skipping merge commits.
+fn changes(from: &str, to: &str, repo_path: &Path) -> anyhow::Result<Vec<Change>> {
+    let log = git_output(
+        &[
+            "log",
+            "--no-merges",
+            "--format=%H%x1f%s%x1f%b%x1e",
+            &format!("{from}..{to}"),
+        ],
+        repo_path,
+    )?;
+    Ok(parse_log(&log))
+}
+
+/// Parses `git log` output of commits formatted as `%H%x1f%s%x1f%b%x1e`.
+fn parse_log(log: &str) -> Vec<Change> {
+    log.split('\x1e')
+        .filter_map(|entry| {
+            let mut fields = entry.trim_start().splitn(3, '\x1f');
+            let commit = fields.next().filter(|commit| !commit.is_empty())?;
+            let subject = fields.next().unwrap_or_default();
+            let body = fields.next().unwrap_or_default();
+            Some(Change::parse(commit, subject, body))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use releasy_core::repo::Repo;
+    use toml::Table;
+
+    use super::{
+        lock_packages, locked_revision, parse_log, ChangeKind, Changelog, LockedRevision,
+        RepoChangelog, UpstreamBump,
+    };
+
+    #[test]
+    fn parse_conventional_commits() {
+        let log = "1a2b3c4d5e\x1ffeat(parser)!: drop the old storage syntax\x1f\x1e\n\
+                   2b3c4d5e6f\x1ffix: handle empty predicates\x1fBREAKING CHANGE: predicates are \
+                   checked\n\x1e\n\
+                   3c4d5e6f7a\x1fUpdate README (#4242)\x1f\x1e\n\
+                   4d5e6f7a8b\x1fperf(vm): cache storage slots\x1f\x1e\n";
+        let changes = parse_log(log);
+        let parsed = changes
+            .iter()
+            .map(|change| {
+                (
+                    change.commit(),
+                    change.kind(),
+                    change.scope.as_deref(),
+                    change.is_breaking(),
+                    change.description.as_str(),
+                )
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            parsed,
+            vec![
+                (
+                    "1a2b3c4d5e",
+                    ChangeKind::Feature,
+                    Some("parser"),
+                    true,
+                    "drop the old storage syntax"
+                ),
+                (
+                    "2b3c4d5e6f",
+                    ChangeKind::Fix,
+                    None,
+                    true,
+                    "handle empty predicates"
+                ),
+                (
+                    "3c4d5e6f7a",
+                    ChangeKind::Other,
+                    None,
+                    false,
+                    "Update README (#4242)"
+                ),
+                (
+                    "4d5e6f7a8b",
+                    ChangeKind::Performance,
+                    Some("vm"),
+                    false,
+                    "cache storage slots"
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn locked_revision_of_upstream_packages() {
+        let lock = r#"
+[[package]]
+name = "fuel-core-client"
+version = "0.20.4"
+source = "registry+https://github.com/rust-lang/crates.io-index"
+
+[[package]]
+name = "fuel-core-types"
+version = "0.20.4"
+source = "registry+https://github.com/rust-lang/crates.io-index"
+
+[[package]]
+name = "fuel-core-metrics"
+version = "0.1.2"
+source = "registry+https://github.com/rust-lang/crates.io-index"
+
+[[package]]
+name = "std"
+source = "git+https://github.com/fuellabs/sway?tag=v0.46.0#337d0eaa"
+
+[[package]]
+name = "forc-wallet"
+version = "0.3.0"
+"#
+        .parse::<Table>()
+        .unwrap();
+        let locked = lock_packages(&lock);
+
+        let fuel_core_packages = ["fuel-core-client", "fuel-core-types", "fuel-core-metrics"]
+            .map(str::to_string)
+            .into_iter()
+            .collect::<HashSet<_>>();
+        let fuel_core = locked_revision(&locked, &fuel_core_packages).unwrap();
+        assert_eq!(fuel_core.label(), "0.20.4");
+        assert_eq!(fuel_core.candidates(), vec!["v0.20.4", "0.20.4"]);
+
+        let sway_packages = HashSet::from(["std".to_string()]);
+        let sway = locked_revision(&locked, &sway_packages).unwrap();
+        assert_eq!(sway.label(), "v0.46.0");
+        assert_eq!(sway.candidates(), vec!["v0.46.0", "337d0eaa"]);
+
+        let fuels_rs_packages = HashSet::from(["fuels".to_string()]);
+        assert_eq!(locked_revision(&locked, &fuels_rs_packages), None);
+    }
+
+    #[test]
+    fn format_changelog_as_markdown() {
+        let sway = Repo::new("sway".to_string(), "FuelLabs".to_string());
+        let fuel_core = Repo::new("fuel-core".to_string(), "FuelLabs".to_string());
+        let version = |version: &str| LockedRevision {
+            version: Some(version.to_string()),
+            ..LockedRevision::default()
+        };
+        let changelog = Changelog {
+            repos: vec![
+                RepoChangelog {
+                    repo: sway,
+                    from: "v0.46.0".to_string(),
+                    to: "v0.47.0".to_string(),
+                    bumps: vec![UpstreamBump {
+                        repo: fuel_core.clone(),
+                        from: Some(version("0.20.4")),
+                        to: Some(version("0.21.0")),
+                    }],
+                    changes: parse_log(
+                        "1a2b3c4d5e\x1ffeat(parser)!: drop the old storage syntax\x1f\x1e\n\
+                         2b3c4d5e6f\x1ffix: handle empty predicates\x1f\x1e",
+                    ),
+                },
+                RepoChangelog {
+                    repo: fuel_core,
+                    from: "v0.20.4".to_string(),
+                    to: "v0.21.0".to_string(),
+                    bumps: vec![],
+                    changes: vec![],
+                },
+            ],
+            warnings: vec![],
+        };
+        assert_eq!(
+            changelog.to_markdown(),
+            "# Changelog of FuelLabs/sway v0.46.0...v0.47.0
+
+## FuelLabs/sway v0.46.0...v0.47.0
+
+### Upstream Bumps
+
+- FuelLabs/fuel-core: 0.20.4 → 0.21.0
+
+### Breaking Changes
+
+- **parser:** drop the old storage syntax (1a2b3c4)
+
+### Bug Fixes
+
+- handle empty predicates (2b3c4d5)
+
+## FuelLabs/fuel-core v0.20.4...v0.21.0
+
+No changes."
+        );
+    }
+}
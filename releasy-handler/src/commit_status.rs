@@ -0,0 +1,104 @@
+//! Commit statuses reporting the CI of tracking branches on the upstream commits they are created
+//! for, so that upstream developers see which downstream repos their commits break.
+//!
+//! The handler posts a pending `releasy/<repo>` status when it handles an upstream commit, and the
+//! CI of the tracking branch completes it with `releasy report-status`.
+use std::{path::Path, process::Command};
+
+use releasy_core::{
+    commit_status::{CommitState, CommitStatus},
+    repo::Repo,
+};
+
+use crate::handle::{block_on, UPSTREAM_TRAILER};
+
+/// Returns the URL of the current GitHub Actions run, if running in GitHub Actions.
+pub fn current_run_url() -> Option<String> {
+    let server_url = std::env::var("GITHUB_SERVER_URL").ok()?;
+    let repository = std::env::var("GITHUB_REPOSITORY").ok()?;
+    let run_id = std::env::var("GITHUB_RUN_ID").ok()?;
+    Some(format!("{server_url}/{repository}/actions/runs/{run_id}"))
+}
+
+/// Returns the upstream repo and commit recorded in the `Releasy-Upstream` trailer of the given
+/// revision, if it is a commit created by releasy.
+pub fn recorded_upstream_commit(
+    rev: &str,
+    repo_path: &Path,
+) -> anyhow::Result<Option<(Repo, String)>> {
+    let output = Command::new("git")
+        .arg("log")
+        .arg("-1")
+        .arg(format!(
+            "--format=%(trailers:key={UPSTREAM_TRAILER},valueonly)"
+        ))
+        .arg(rev)
+        .current_dir(repo_path)
+        .output()?;
+    if !output.status.success() {
+        anyhow::bail!("cannot read the commit message of {rev}");
+    }
+    Ok(String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .find_map(parse_upstream_trailer))
+}
+
+/// Parses a `<owner>/<name>@<commit-hash>` trailer value.
+fn parse_upstream_trailer(value: &str) -> Option<(Repo, String)> {
+    let (repo, commit_hash) = value.trim().split_once('@')?;
+    let (owner, name) = repo.split_once('/')?;
+    if owner.is_empty() || name.is_empty() || commit_hash.is_empty() {
+        return None;
+    }
+    Some((
+        Repo::new(name.to_string(), owner.to_string()),
+        commit_hash.to_string(),
+    ))
+}
+
+/// Posts a commit status on the upstream commit using `DISPATCH_TOKEN`, which grants access to
+/// the repos of the plan.
+pub async fn post_commit_status(
+    upstream: &Repo,
+    commit_hash: &str,
+    status: &CommitStatus,
+) -> anyhow::Result<()> {
+    let github_token = std::env::var("DISPATCH_TOKEN")
+        .map_err(|_| anyhow::anyhow!("DISPATCH_TOKEN should be set to post commit statuses"))?;
+    status.post(upstream, commit_hash, &github_token).await?;
+    Ok(())
+}
+
+/// Posts a pending status of the current repo on the upstream commit, linking to the current run.
+///
+/// Reporting is best effort, failures are only printed so that they do not stop handling.
+pub(crate) fn report_pending(upstream: &Repo, commit_hash: &str, current_repo: &Repo) {
+    let status = CommitStatus::new(CommitState::Pending, current_repo)
+        .with_description(Some("Tracking branch updated, waiting for CI".to_string()))
+        .with_target_url(current_run_url());
+    let (upstream, commit_hash) = (upstream.clone(), commit_hash.to_string());
+    let result =
+        block_on(async move { post_commit_status(&upstream, &commit_hash, &status).await });
+    if let Err(error) = result {
+        println!("WARNING: cannot post the commit status: {error}");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::parse_upstream_trailer;
+    use releasy_core::repo::Repo;
+
+    #[test]
+    fn parse_upstream_trailer_value() {
+        assert_eq!(
+            parse_upstream_trailer("FuelLabs/fuel-core@9e3b1c7a\n"),
+            Some((
+                Repo::new("fuel-core".to_string(), "FuelLabs".to_string()),
+                "9e3b1c7a".to_string()
+            ))
+        );
+        assert_eq!(parse_upstream_trailer("fuel-core@9e3b1c7a"), None);
+        assert_eq!(parse_upstream_trailer("FuelLabs/fuel-core@"), None);
+    }
+}
@@ -0,0 +1,197 @@
+//! Commands run on tracking branches after they are brought up to date, e.g. to update lock files
+//! or regenerate code for the new upstream, before the CI is triggered.
+use std::{io::Write, path::Path, process::Command};
+
+use releasy_core::repo::Repo;
+use releasy_graph::manifest::RepoSettings;
+
+use crate::{
+    handle::ReleasyHandlerCommand,
+    patch::{TrackingPatches, UpstreamPatch},
+};
+
+/// Environment variable GitHub Actions reads the Markdown summary of the job from.
+const STEP_SUMMARY_ENV: &str = "GITHUB_STEP_SUMMARY";
+
+/// Details of the update passed to the hooks with the following env variables:
+///
+/// ```text
+/// RELEASY_TRACKING_BRANCH
+/// RELEASY_UPSTREAM_REPO      (as <owner>/<name>, if the branch tracks a known upstream)
+/// RELEASY_UPSTREAM_COMMIT    (if the update is caused by an upstream commit)
+/// ```
+#[derive(Clone, Debug)]
+pub(crate) struct HookEnv<'a> {
+    pub(crate) tracking_branch: &'a str,
+    pub(crate) upstream: Option<&'a Repo>,
+    pub(crate) upstream_commit: Option<&'a str>,
+}
+
+impl<'a> HookEnv<'a> {
+    /// Creates the env of an update not caused by an upstream commit.
+    pub(crate) fn for_branch(tracking_branch: &'a str) -> Self {
+        Self {
+            tracking_branch,
+            upstream: None,
+            upstream_commit: None,
+        }
+    }
+}
+
+/// A hook that did not succeed.
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct HookFailure {
+    hook: String,
+    error: String,
+}
+
+/// Runs the hooks of the repo and then the ones of the edges tracked by the checked out tracking
+/// branch, staging the changes of each hook. The staged changes are committed by the commit
+/// triggering the CI, so that hooks do not add commits to the branch.
+///
+/// Failing hooks do not stop the update: their changes are discarded, and they are reported in
+/// the step summary of the job so that the CI of the branch can be interpreted.
+pub(crate) fn run_hooks(
+    env: &HookEnv,
+    settings: &RepoSettings,
+    patches: &TrackingPatches,
+    repo_path: &Path,
+) -> anyhow::Result<()> {
+    let handler_settings = settings.handler();
+    let branch_patches = patches
+        .get(env.tracking_branch)
+        .map(Vec::as_slice)
+        .unwrap_or_default();
+    let hooks = handler_settings
+        .hooks()
+        .chain(branch_patches.iter().flat_map(UpstreamPatch::hooks))
+        .collect::<Vec<_>>();
+    let upstream = env
+        .upstream
+        .or_else(|| branch_patches.first().map(UpstreamPatch::upstream));
+
+    let mut failures = vec![];
+    for hook in hooks {
+        println!("Running `{hook}` on {}", env.tracking_branch);
+        let mut command = Command::new("sh");
+        command
+            .arg("-c")
+            .arg(hook)
+            .env("RELEASY_TRACKING_BRANCH", env.tracking_branch)
+            .current_dir(repo_path);
+        if let Some(upstream) = upstream {
+            command.env(
+                "RELEASY_UPSTREAM_REPO",
+                format!("{}/{}", upstream.owner(), upstream.name()),
+            );
+        }
+        if let Some(upstream_commit) = env.upstream_commit {
+            command.env("RELEASY_UPSTREAM_COMMIT", upstream_commit);
+        }
+
+        let error = match command.status() {
+            Ok(status) if status.success() => None,
+            Ok(status) => Some(match status.code() {
+                Some(code) => format!("exited with code {code}"),
+                None => "terminated by a signal".to_string(),
+            }),
+            Err(error) => Some(error.to_string()),
+        };
+        match error {
+            None => {
+                ReleasyHandlerCommand::new("git")
+                    .arg("add")
+                    .arg("-A")
+                    .current_dir(repo_path)
+                    .execute()?;
+            }
+            Some(error) => {
+                println!("WARNING: `{hook}` {error}, discarding its changes");
+                discard_changes(repo_path)?;
+                failures.push(HookFailure {
+                    hook: hook.clone(),
+                    error,
+                });
+            }
+        }
+    }
+
+    if !failures.is_empty() {
+        report_failures(env.tracking_branch, &failures)?;
+    }
+    Ok(())
+}
+
+/// Resets the working tree to the changes staged so far, keeping ignored files such as build
+/// caches.
+fn discard_changes(repo_path: &Path) -> anyhow::Result<()> {
+    ReleasyHandlerCommand::new("git")
+        .arg("checkout")
+        .arg("--")
+        .arg(".")
+        .current_dir(repo_path)
+        .execute()?;
+    ReleasyHandlerCommand::new("git")
+        .arg("clean")
+        .arg("-fd")
+        .current_dir(repo_path)
+        .execute()
+}
+
+/// Appends the failures to the step summary of the job, if running in GitHub Actions.
+fn report_failures(tracking_branch: &str, failures: &[HookFailure]) -> anyhow::Result<()> {
+    let Ok(summary_path) = std::env::var(STEP_SUMMARY_ENV) else {
+        return Ok(());
+    };
+    let mut summary = std::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(summary_path)?;
+    write!(
+        summary,
+        "{}",
+        failures_to_markdown(tracking_branch, failures)
+    )?;
+    Ok(())
+}
+
+/// Formats the failures of the hooks run on a tracking branch as a Markdown section.
+fn failures_to_markdown(tracking_branch: &str, failures: &[HookFailure]) -> String {
+    let mut markdown =
+        format!("### Failed hooks on `{tracking_branch}`\n\n| Hook | Error |\n| --- | --- |\n");
+    for failure in failures {
+        markdown.push_str(&format!(
+            "| `{}` | {} |\n",
+            failure.hook.replace('|', "\\|"),
+            failure.error
+        ));
+    }
+    markdown
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{failures_to_markdown, HookFailure};
+
+    #[test]
+    fn format_hook_failures() {
+        let failures = vec![
+            HookFailure {
+                hook: "cargo update -p fuel-core-client".to_string(),
+                error: "exited with code 101".to_string(),
+            },
+            HookFailure {
+                hook: "./codegen.sh | tee codegen.log".to_string(),
+                error: "terminated by a signal".to_string(),
+            },
+        ];
+        assert_eq!(
+            failures_to_markdown("upgrade/fuel-core-master", &failures),
+            "### Failed hooks on `upgrade/fuel-core-master`\n\n\
+             | Hook | Error |\n\
+             | --- | --- |\n\
+             | `cargo update -p fuel-core-client` | exited with code 101 |\n\
+             | `./codegen.sh \\| tee codegen.log` | terminated by a signal |\n"
+        );
+    }
+}
@@ -0,0 +1,74 @@
+//! A library for handling releasy events received by the current repo.
+//!
+//! Tools embedding the handler can use `EventHandler` directly:
+//!
+//! ```no_run
+//! use releasy_core::event::Event;
+//! use releasy_graph::manifest::ManifestFile;
+//! use releasy_handler::handle::EventHandler;
+//!
+//! # fn handle(event: Event) -> anyhow::Result<()> {
+//! let manifest = ManifestFile::from_file("repo-plan.toml".as_ref())?.manifest();
+//! event.handle(manifest)?;
+//! # Ok(())
+//! # }
+//! ```
+pub mod changelog;
+pub mod commit_status;
+pub mod handle;
+mod hooks;
+mod patch;
+pub mod reconcile;
+pub mod release_train;
+pub mod serve;
+pub mod status;
+mod version;
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use releasy_graph::{manifest::ManifestFile, plan::Plan};
+
+    const SWAY_WALLET_SDK_TEST_MANIFEST_FILE_NAME: &str = "repo-plan-sway-wallet-sdk.toml";
+
+    /// In this test we have:
+    ///  - forc-wallet
+    ///  - sway
+    ///  - fuels-rs
+    ///
+    /// repositories present. The dependency graph between them looks like:
+    ///
+    /// ```text
+    /// forc-wallet -> fuels-rs
+    /// sway -> fuels-rs
+    /// sway -> forc-wallet
+    /// ```
+    ///
+    /// and the `current_repo` is `sway`.
+    ///
+    /// This is a simple example and the circular dependency between the sdk and sway is omitted.
+    #[test]
+    fn sway_wallet_sdk_example_test_event_order() {
+        let test_manifest_file = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+            .join("tests")
+            .join(SWAY_WALLET_SDK_TEST_MANIFEST_FILE_NAME);
+
+        let manifest = ManifestFile::from_file(&test_manifest_file)
+            .unwrap()
+            .manifest();
+        let current_repo = manifest.current_repo().clone();
+        assert_eq!(current_repo.name(), "sway");
+
+        let plan = Plan::try_from_manifest(manifest).unwrap();
+
+        let upstream_repos = plan
+            .upstream_repos(current_repo)
+            .unwrap()
+            .map(|repo| repo.name())
+            .collect::<Vec<_>>();
+        let expected_target_repos = vec!["forc-wallet", "fuels-rs"];
+
+        assert_eq!(upstream_repos, expected_target_repos)
+    }
+}
@@ -0,0 +1,343 @@
+//! Declarative patches applied to tracking branches regenerated with the `recreate` branch
+//! strategy.
+use std::{collections::BTreeMap, path::Path, process::Command};
+
+use releasy_core::repo::Repo;
+use releasy_graph::{
+    manifest::{Ecosystem, FileEdit, Manifest},
+    plan::Plan,
+};
+use toml_edit::{Item, TableLike};
+
+use crate::handle::ReleasyHandlerCommand;
+
+/// Changes making a tracking branch use the tracked branch of an upstream repo.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) struct UpstreamPatch {
+    upstream: Repo,
+    upstream_branch: String,
+    crates: Vec<String>,
+    files: Vec<FileEdit>,
+    hooks: Vec<String>,
+}
+
+impl UpstreamPatch {
+    pub(crate) fn upstream(&self) -> &Repo {
+        &self.upstream
+    }
+
+    /// Returns the commands run on the tracking branch for this upstream.
+    pub(crate) fn hooks(&self) -> impl Iterator<Item = &String> {
+        self.hooks.iter()
+    }
+
+    /// Returns the git source of the upstream used in the patch section.
+    fn upstream_url(&self) -> String {
+        format!(
+            "https://github.com/{}/{}",
+            self.upstream.owner(),
+            self.upstream.name()
+        )
+    }
+}
+
+/// Patches applied to each tracking branch of a repo, keyed by tracking branch.
+pub(crate) type TrackingPatches = BTreeMap<String, Vec<UpstreamPatch>>;
+
+/// Collects the patches of every tracking branch of the current repo from the edges of the plan.
+pub(crate) fn tracking_patches(
+    manifest: &Manifest,
+    plan: &Plan,
+) -> anyhow::Result<TrackingPatches> {
+    let mut patches = TrackingPatches::new();
+    for (upstream, edge) in plan.upstream_edges(manifest.current_repo().clone())? {
+        let spec = edge.patch();
+        let crates = match spec.crates() {
+            Some(crates) => crates.to_vec(),
+            None => manifest.settings(upstream).crates().cloned().collect(),
+        };
+        for (upstream_branch, tracking_branch) in
+            edge.branch_mapping(upstream, plan.default_branch(upstream))
+        {
+            patches
+                .entry(tracking_branch)
+                .or_default()
+                .push(UpstreamPatch {
+                    upstream: upstream.clone(),
+                    upstream_branch,
+                    crates: crates.clone(),
+                    files: spec.files().cloned().collect(),
+                    hooks: edge.hooks().cloned().collect(),
+                });
+        }
+    }
+    Ok(patches)
+}
+
+/// Applies the patches to the checkout, editing the package manifest of the ecosystem and writing
+/// the declared files.
+pub(crate) fn apply_patches(
+    patches: &[UpstreamPatch],
+    ecosystem: &Ecosystem,
+    repo_path: &Path,
+) -> anyhow::Result<()> {
+    let manifest_file_name = match ecosystem {
+        Ecosystem::Cargo => Some("Cargo.toml"),
+        Ecosystem::Forc => Some("Forc.toml"),
+        Ecosystem::Npm => None,
+    };
+    if let Some(manifest_file_name) = manifest_file_name {
+        let manifest_path = repo_path.join(manifest_file_name);
+        let mut contents = std::fs::read_to_string(&manifest_path)?;
+        for patch in patches.iter().filter(|patch| !patch.crates.is_empty()) {
+            contents = patch_manifest(&contents, ecosystem, patch)?;
+        }
+        std::fs::write(&manifest_path, contents)?;
+    }
+    for file in patches.iter().flat_map(|patch| &patch.files) {
+        let path = repo_path.join(file.path());
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(path, file.contents())?;
+    }
+    Ok(())
+}
+
+/// Adds the crates of the upstream to the `[patch]` section of a `Cargo.toml` or `Forc.toml`,
+/// pointing them to the tracked branch of the upstream. Formatting of the rest of the file is
+/// preserved.
+fn patch_manifest(
+    contents: &str,
+    ecosystem: &Ecosystem,
+    patch: &UpstreamPatch,
+) -> anyhow::Result<String> {
+    let upstream_url = patch.upstream_url();
+    // Cargo patches crates from crates.io, while forc patches the git source of the upstream.
+    let source = match ecosystem {
+        Ecosystem::Forc => upstream_url.to_lowercase(),
+        Ecosystem::Cargo | Ecosystem::Npm => "crates-io".to_string(),
+    };
+    let mut document = contents.parse::<toml_edit::Document>()?;
+    let patch_table = document
+        .entry("patch")
+        .or_insert(toml_edit::table())
+        .as_table_mut()
+        .ok_or_else(|| anyhow::anyhow!("`patch` should be a table"))?;
+    patch_table.set_implicit(true);
+    let source_table = patch_table
+        .entry(&source)
+        .or_insert(toml_edit::table())
+        .as_table_mut()
+        .ok_or_else(|| anyhow::anyhow!("`patch.{source}` should be a table"))?;
+    for name in &patch.crates {
+        let mut entry = toml_edit::InlineTable::new();
+        entry.insert("git", upstream_url.as_str().into());
+        entry.insert("branch", patch.upstream_branch.as_str().into());
+        source_table.insert(name, toml_edit::value(entry));
+    }
+    Ok(document.to_string())
+}
+
+/// Pins the git sources of the upstream in every package manifest of the checkout to the given
+/// commit, and updates `Cargo.lock` accordingly. Changes are staged so that they are part of the
+/// next commit.
+pub(crate) fn pin_upstream_commit(
+    upstream: &Repo,
+    commit_hash: &str,
+    ecosystem: &Ecosystem,
+    repo_path: &Path,
+) -> anyhow::Result<()> {
+    let manifest_file_name = match ecosystem {
+        Ecosystem::Cargo => "Cargo.toml",
+        Ecosystem::Forc => "Forc.toml",
+        Ecosystem::Npm => anyhow::bail!("pinning upstream commits is not supported for npm"),
+    };
+    let output = Command::new("git")
+        .arg("ls-files")
+        .arg(format!(":(glob)**/{manifest_file_name}"))
+        .current_dir(repo_path)
+        .output()?;
+    if !output.status.success() {
+        anyhow::bail!("cannot list the {manifest_file_name} files of the checkout");
+    }
+    let mut pinned_any = false;
+    for manifest_path in String::from_utf8_lossy(&output.stdout).lines() {
+        let manifest_path = repo_path.join(manifest_path);
+        let contents = std::fs::read_to_string(&manifest_path)?;
+        let (contents, pinned) = pin_manifest(&contents, upstream, commit_hash)?;
+        if !pinned.is_empty() {
+            println!("Pinning {} to {commit_hash}", pinned.join(", "));
+            std::fs::write(&manifest_path, contents)?;
+            pinned_any = true;
+        }
+    }
+    if !pinned_any {
+        println!("WARNING: no git source of the upstream found to pin to {commit_hash}");
+        return Ok(());
+    }
+
+    // Only the workspace packages are re-resolved, so that other locked versions are kept.
+    if *ecosystem == Ecosystem::Cargo && repo_path.join("Cargo.lock").exists() {
+        ReleasyHandlerCommand::new("cargo")
+            .arg("update")
+            .arg("--workspace")
+            .current_dir(repo_path)
+            .execute()?;
+    }
+    ReleasyHandlerCommand::new("git")
+        .arg("add")
+        .arg("-A")
+        .current_dir(repo_path)
+        .execute()
+}
+
+/// Sets `rev = "<commit_hash>"` on every dependency or patch entry of the manifest whose git
+/// source is the upstream, dropping their `branch` and `tag`. Returns the manifest and the names
+/// of the pinned packages.
+fn pin_manifest(
+    contents: &str,
+    upstream: &Repo,
+    commit_hash: &str,
+) -> anyhow::Result<(String, Vec<String>)> {
+    let mut document = contents.parse::<toml_edit::Document>()?;
+    let mut pinned = vec![];
+    pin_git_sources(document.as_table_mut(), upstream, commit_hash, &mut pinned);
+    Ok((document.to_string(), pinned))
+}
+
+fn pin_git_sources(
+    table: &mut dyn TableLike,
+    upstream: &Repo,
+    commit_hash: &str,
+    pinned: &mut Vec<String>,
+) {
+    for (key, item) in table.iter_mut() {
+        let Some(entry) = item.as_table_like_mut() else {
+            continue;
+        };
+        let is_upstream = entry
+            .get("git")
+            .and_then(Item::as_str)
+            .is_some_and(|git| is_upstream_source(git, upstream));
+        if !is_upstream {
+            pin_git_sources(entry, upstream, commit_hash, pinned);
+            continue;
+        }
+        entry.remove("branch");
+        entry.remove("tag");
+        entry.insert("rev", toml_edit::value(commit_hash));
+        let package = entry
+            .get("package")
+            .and_then(Item::as_str)
+            .unwrap_or(key.get());
+        pinned.push(package.to_string());
+        // Removing the last key of an inline table leaves its whitespace behind.
+        if let Some(inline_table) = item.as_inline_table_mut() {
+            inline_table.fmt();
+        }
+    }
+}
+
+/// Returns true if the git URL points to the GitHub repo of the upstream.
+fn is_upstream_source(git: &str, upstream: &Repo) -> bool {
+    let git = git.to_lowercase();
+    let git = git.trim_end_matches('/').trim_end_matches(".git");
+    let repo_path = format!("github.com/{}/{}", upstream.owner(), upstream.name()).to_lowercase();
+    git.strip_suffix(&repo_path).is_some_and(|prefix| {
+        prefix.ends_with("://") || prefix.ends_with('@') || prefix.ends_with('/')
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{patch_manifest, pin_manifest, UpstreamPatch};
+    use releasy_core::repo::Repo;
+    use releasy_graph::manifest::Ecosystem;
+
+    fn fuel_core_patch() -> UpstreamPatch {
+        UpstreamPatch {
+            upstream: Repo::new("fuel-core".to_string(), "FuelLabs".to_string()),
+            upstream_branch: "master".to_string(),
+            crates: vec!["fuel-core-client".to_string()],
+            files: vec![],
+            hooks: vec![],
+        }
+    }
+
+    #[test]
+    fn patch_cargo_manifest() {
+        let contents = r#"# Workspace of the SDK.
+[workspace]
+members = ["packages/fuels"]
+
+[patch.crates-io]
+fuel-vm = { path = "../fuel-vm" }
+"#;
+        let patched = patch_manifest(contents, &Ecosystem::Cargo, &fuel_core_patch()).unwrap();
+        assert_eq!(
+            patched,
+            r#"# Workspace of the SDK.
+[workspace]
+members = ["packages/fuels"]
+
+[patch.crates-io]
+fuel-vm = { path = "../fuel-vm" }
+fuel-core-client = { git = "https://github.com/FuelLabs/fuel-core", branch = "master" }
+"#
+        );
+    }
+
+    #[test]
+    fn patch_forc_manifest() {
+        let contents = "[project]\nname = \"app\"\n";
+        let patch = UpstreamPatch {
+            upstream: Repo::new("sway".to_string(), "FuelLabs".to_string()),
+            upstream_branch: "master".to_string(),
+            crates: vec!["std".to_string()],
+            files: vec![],
+            hooks: vec![],
+        };
+        let patched = patch_manifest(contents, &Ecosystem::Forc, &patch).unwrap();
+        assert!(patched.contains(
+            "[patch.\"https://github.com/fuellabs/sway\"]\nstd = { git = \"https://github.com/FuelLabs/sway\", branch = \"master\" }"
+        ));
+    }
+
+    #[test]
+    fn pin_upstream_git_sources() {
+        let contents = r#"[workspace.dependencies]
+fuel-core = { version = "0.22", default-features = false }
+fuel-vm = { git = "https://github.com/FuelLabs/fuel-vm", branch = "master" }
+
+[patch.crates-io]
+fuel-core-client = { git = "https://github.com/FuelLabs/fuel-core", branch = "master" }
+client = { git = "https://github.com/fuellabs/fuel-core.git", tag = "v0.22.0", package = "fuel-core-types" }
+
+[dependencies.fuel-core-p2p]
+git = "https://github.com/FuelLabs/fuel-core"
+branch = "master"
+"#;
+        let fuel_core = Repo::new("fuel-core".to_string(), "FuelLabs".to_string());
+        let (pinned_contents, pinned) = pin_manifest(contents, &fuel_core, "9e3b1c7a").unwrap();
+        assert_eq!(
+            pinned_contents,
+            r#"[workspace.dependencies]
+fuel-core = { version = "0.22", default-features = false }
+fuel-vm = { git = "https://github.com/FuelLabs/fuel-vm", branch = "master" }
+
+[patch.crates-io]
+fuel-core-client = { git = "https://github.com/FuelLabs/fuel-core", rev = "9e3b1c7a" }
+client = { git = "https://github.com/fuellabs/fuel-core.git", package = "fuel-core-types", rev = "9e3b1c7a" }
+
+[dependencies.fuel-core-p2p]
+git = "https://github.com/FuelLabs/fuel-core"
+rev = "9e3b1c7a"
+"#
+        );
+        assert_eq!(
+            pinned,
+            vec!["fuel-core-client", "fuel-core-types", "fuel-core-p2p"]
+        );
+    }
+}
@@ -0,0 +1,441 @@
+//! Convergence of the tracking branches of the current repo to the state described by the plan.
+//!
+//! Tracking branches are otherwise only updated when an event arrives, so missed events, new
+//! dependencies and removed dependencies leave them stale or orphaned.
+use std::{collections::BTreeMap, fmt::Display, path::Path};
+
+use releasy_core::repo::Repo;
+use releasy_graph::{
+    manifest::{DependencyDetails, Manifest, RepoSettings},
+    plan::Plan,
+};
+use serde::Serialize;
+
+use crate::{
+    handle::{
+        commit_staged_changes, discard_changes, git_output, push_tracking_branch,
+        redact_credentials, update_tracking_branch, with_repo, ReleasyHandlerCommand, Workspace,
+        UPSTREAM_TRAILER,
+    },
+    hooks::{run_hooks, HookEnv},
+    patch::{tracking_patches, TrackingPatches},
+};
+
+/// Prefix of the tracking branches created by releasy. Branches with this prefix that are not
+/// described by the plan are considered orphaned if their latest commit was created by releasy.
+pub const TRACKING_BRANCH_PREFIX: &str = "upgrade/";
+
+/// What reconciling does to a tracking branch.
+#[derive(Serialize, Clone, Debug, PartialEq, Eq)]
+#[serde(tag = "action", rename_all = "kebab-case")]
+pub enum BranchAction {
+    /// The branch is missing on the remote and is created from its base branch.
+    Create,
+    /// The branch is behind its base branch by the given number of commits, and is rebased.
+    Rebase {
+        behind: usize,
+    },
+    UpToDate,
+    /// The branch does not track any dependency of the plan.
+    Orphaned {
+        deleted: bool,
+    },
+}
+
+/// A tracking branch of the current repo and how it is reconciled.
+#[derive(Serialize, Clone, Debug, PartialEq, Eq)]
+pub struct BranchState {
+    branch: String,
+    /// The dependency tracked by the branch, missing for orphaned branches.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    upstream: Option<Repo>,
+    #[serde(flatten)]
+    action: BranchAction,
+    /// Why the action failed, the other branches are reconciled regardless.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+impl BranchState {
+    pub fn branch(&self) -> &str {
+        &self.branch
+    }
+
+    pub fn upstream(&self) -> Option<&Repo> {
+        self.upstream.as_ref()
+    }
+
+    pub fn action(&self) -> &BranchAction {
+        &self.action
+    }
+
+    pub fn error(&self) -> Option<&str> {
+        self.error.as_deref()
+    }
+}
+
+/// Flags controlling how tracking branches are reconciled.
+#[derive(Clone, Debug, Default)]
+pub struct ReconcileOptions {
+    /// Only report what would be done, without pushing anything.
+    pub dry_run: bool,
+    /// Delete orphaned branches from the remote instead of only reporting them.
+    pub delete_orphaned: bool,
+}
+
+/// Outcome of reconciling every tracking branch of the current repo.
+#[derive(Serialize, Clone, Debug, Default, PartialEq, Eq)]
+pub struct ReconcileReport {
+    dry_run: bool,
+    branches: Vec<BranchState>,
+}
+
+impl ReconcileReport {
+    /// Returns an iterator over the state of each tracking branch.
+    pub fn branches(&self) -> impl Iterator<Item = &BranchState> {
+        self.branches.iter()
+    }
+
+    /// Returns the number of branches whose action failed.
+    pub fn failures(&self) -> usize {
+        self.branches
+            .iter()
+            .filter(|state| state.error.is_some())
+            .count()
+    }
+}
+
+impl Display for ReconcileReport {
+    /// Formats the report as a table.
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let branch_width = self
+            .branches
+            .iter()
+            .map(|state| state.branch.len())
+            .chain(std::iter::once("BRANCH".len()))
+            .max()
+            .unwrap_or_default();
+        writeln!(f, "{:branch_width$}  {:10}  DETAILS", "BRANCH", "ACTION")?;
+        for state in &self.branches {
+            let (action, details) = match &state.action {
+                BranchAction::Create => ("create", String::new()),
+                BranchAction::Rebase { behind } => {
+                    ("rebase", format!("{behind} commits behind its base branch"))
+                }
+                BranchAction::UpToDate => ("up-to-date", String::new()),
+                BranchAction::Orphaned { deleted: true } => ("delete", String::new()),
+                BranchAction::Orphaned { deleted: false } => {
+                    ("orphaned", "no dependency tracked".to_string())
+                }
+            };
+            let details = match (&state.error, &state.upstream) {
+                (Some(error), _) => format!("failed: {error}"),
+                (None, Some(upstream)) if details.is_empty() => {
+                    format!("tracks {}/{}", upstream.owner(), upstream.name())
+                }
+                _ => details,
+            };
+            writeln!(f, "{:branch_width$}  {action:10}  {details}", state.branch)?;
+        }
+        if self.dry_run {
+            write!(f, "dry run, nothing is pushed")?;
+        }
+        Ok(())
+    }
+}
+
+/// Compares the tracking branches described by the plan with the ones on the remote, and
+/// creates, rebases or deletes them so that they converge.
+pub fn reconcile(
+    manifest: Manifest,
+    options: &ReconcileOptions,
+    workspace: &Workspace,
+) -> anyhow::Result<ReconcileReport> {
+    let current_repo = manifest.current_repo().clone();
+    let settings = manifest.settings(&current_repo);
+    let plan = Plan::try_from_manifest(manifest.clone())?;
+    let patches = tracking_patches(&manifest, &plan)?;
+    let desired = plan
+        .upstream_edges(current_repo.clone())?
+        .flat_map(|(upstream, edge)| {
+            edge.tracking_branches(upstream, plan.default_branch(upstream))
+                .into_iter()
+                .map(|branch| (branch, upstream.clone()))
+        })
+        .collect::<BTreeMap<_, _>>();
+    // Base branches of the tracking branches that are not based on the default branch.
+    let base_branches = plan
+        .upstream_edges(current_repo.clone())?
+        .flat_map(|(upstream, edge)| edge.tracking_bases(upstream, plan.default_branch(upstream)))
+        .filter_map(|(_, branch, base_branch)| Some((branch, base_branch?)))
+        .collect::<BTreeMap<_, _>>();
+
+    let mut report = ReconcileReport {
+        dry_run: options.dry_run,
+        branches: vec![],
+    };
+    with_repo(
+        "reconcile",
+        &current_repo,
+        &settings,
+        workspace,
+        |repo_path, default_branch| {
+            let base_branch = |branch: &str| {
+                base_branches
+                    .get(branch)
+                    .map_or(default_branch, String::as_str)
+                    .to_string()
+            };
+            let existing = remote_tracking_branches(
+                &desired,
+                base_branch,
+                settings.handler().commit_author_email(),
+                repo_path,
+            )?;
+            report.branches = branch_states(&desired, &existing, options.delete_orphaned);
+            if options.dry_run {
+                return Ok(());
+            }
+            for state in &mut report.branches {
+                if let Err(e) = reconcile_branch(state, base_branch, &settings, &patches, repo_path)
+                {
+                    println!("ERROR: failed to reconcile {}: {e}", state.branch);
+                    state.error = Some(redact_credentials(&e.to_string()));
+                    discard_changes(repo_path)?;
+                }
+            }
+            Ok(())
+        },
+    )?;
+    Ok(report)
+}
+
+/// Creates, rebases or deletes the branch as decided by its state.
+fn reconcile_branch(
+    state: &BranchState,
+    base_branch: impl Fn(&str) -> String,
+    settings: &RepoSettings,
+    patches: &TrackingPatches,
+    repo_path: &Path,
+) -> anyhow::Result<()> {
+    match state.action {
+        BranchAction::Create | BranchAction::Rebase { .. } => {
+            println!("Updating {}", state.branch);
+            update_tracking_branch(
+                &state.branch,
+                &base_branch(&state.branch),
+                settings,
+                patches,
+                repo_path,
+            )?;
+            run_hooks(
+                &HookEnv::for_branch(&state.branch),
+                settings,
+                patches,
+                repo_path,
+            )?;
+            commit_staged_changes(&state.branch, settings, repo_path)?;
+            push_tracking_branch(&state.branch, &settings.handler(), repo_path)
+        }
+        BranchAction::Orphaned { deleted: true } => {
+            println!("Deleting {}", state.branch);
+            ReleasyHandlerCommand::new("git")
+                .arg("push")
+                .arg("origin")
+                .arg("--delete")
+                .arg(&state.branch)
+                .current_dir(repo_path)
+                .execute()
+        }
+        BranchAction::UpToDate | BranchAction::Orphaned { deleted: false } => Ok(()),
+    }
+}
+
+/// Returns the tracking branches present on the remote, either desired or created by releasy,
+/// with the number of commits of their base branch they are missing.
+///
+/// `upgrade/*` branches that are not desired are only returned if their latest commit was created
+/// by releasy, so that branches pushed by people or other bots are never deleted.
+fn remote_tracking_branches(
+    desired: &BTreeMap<String, Repo>,
+    base_branch: impl Fn(&str) -> String,
+    commit_author_email: &str,
+    repo_path: &Path,
+) -> anyhow::Result<BTreeMap<String, usize>> {
+    let output = git_output(
+        &[
+            "for-each-ref",
+            "--format=%(refname:strip=3)",
+            "refs/remotes/origin/",
+        ],
+        repo_path,
+    )?;
+    let mut existing = BTreeMap::new();
+    for branch in output.lines() {
+        if !branch.starts_with(TRACKING_BRANCH_PREFIX) && !desired.contains_key(branch) {
+            continue;
+        }
+        // Pull request branches are deleted when their pull request is closed.
+        if DependencyDetails::is_pull_request_branch(branch) {
+            continue;
+        }
+        if !desired.contains_key(branch) {
+            let tip = git_output(
+                &[
+                    "log",
+                    "-1",
+                    &format!("--format=%ae%n%(trailers:key={UPSTREAM_TRAILER},valueonly)"),
+                    &format!("origin/{branch}"),
+                ],
+                repo_path,
+            )?;
+            if !created_by_releasy(&tip, commit_author_email) {
+                continue;
+            }
+        }
+        let behind = git_output(
+            &[
+                "rev-list",
+                "--count",
+                &format!("origin/{branch}..origin/{}", base_branch(branch)),
+            ],
+            repo_path,
+        )?
+        .parse()?;
+        existing.insert(branch.to_string(), behind);
+    }
+    Ok(existing)
+}
+
+/// Returns true if the commit, given as its author email followed by its upstream trailers, was
+/// created by releasy: either it records an upstream commit or it is authored by the handler.
+fn created_by_releasy(tip: &str, commit_author_email: &str) -> bool {
+    let mut lines = tip.lines();
+    let author_email = lines.next().unwrap_or_default();
+    author_email == commit_author_email || lines.any(|trailer| !trailer.trim().is_empty())
+}
+
+/// Decides how each tracking branch is reconciled, given the desired branches with the dependency
+/// they track, and the existing branches with the number of commits they are behind.
+fn branch_states(
+    desired: &BTreeMap<String, Repo>,
+    existing: &BTreeMap<String, usize>,
+    delete_orphaned: bool,
+) -> Vec<BranchState> {
+    let desired_states = desired.iter().map(|(branch, upstream)| {
+        let action = match existing.get(branch) {
+            None => BranchAction::Create,
+            Some(0) => BranchAction::UpToDate,
+            Some(behind) => BranchAction::Rebase { behind: *behind },
+        };
+        BranchState {
+            branch: branch.clone(),
+            upstream: Some(upstream.clone()),
+            action,
+            error: None,
+        }
+    });
+    let orphaned_states = existing
+        .keys()
+        .filter(|branch| !desired.contains_key(*branch))
+        .map(|branch| BranchState {
+            branch: branch.clone(),
+            upstream: None,
+            action: BranchAction::Orphaned {
+                deleted: delete_orphaned,
+            },
+            error: None,
+        });
+    desired_states.chain(orphaned_states).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{branch_states, created_by_releasy, BranchAction, ReconcileReport};
+    use releasy_core::repo::Repo;
+    use std::collections::BTreeMap;
+
+    #[test]
+    fn decide_branch_actions() {
+        let repo = |name: &str| Repo::new(name.to_string(), "FuelLabs".to_string());
+        let desired = BTreeMap::from([
+            ("upgrade/fuel-core-master".to_string(), repo("fuel-core")),
+            ("upgrade/sway-master".to_string(), repo("sway")),
+            (
+                "upgrade/forc-wallet-master".to_string(),
+                repo("forc-wallet"),
+            ),
+        ]);
+        let existing = BTreeMap::from([
+            ("upgrade/fuel-core-master".to_string(), 0),
+            ("upgrade/sway-master".to_string(), 3),
+            ("upgrade/fuel-vm-master".to_string(), 12),
+        ]);
+
+        let actions = branch_states(&desired, &existing, false)
+            .into_iter()
+            .map(|state| (state.branch().to_string(), state.action().clone()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            actions,
+            vec![
+                (
+                    "upgrade/forc-wallet-master".to_string(),
+                    BranchAction::Create
+                ),
+                (
+                    "upgrade/fuel-core-master".to_string(),
+                    BranchAction::UpToDate
+                ),
+                (
+                    "upgrade/sway-master".to_string(),
+                    BranchAction::Rebase { behind: 3 }
+                ),
+                (
+                    "upgrade/fuel-vm-master".to_string(),
+                    BranchAction::Orphaned { deleted: false }
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn report_failed_branches() {
+        let repo = |name: &str| Repo::new(name.to_string(), "FuelLabs".to_string());
+        let desired = BTreeMap::from([
+            ("upgrade/fuel-core-master".to_string(), repo("fuel-core")),
+            ("upgrade/sway-master".to_string(), repo("sway")),
+        ]);
+        let existing = BTreeMap::from([("upgrade/sway-master".to_string(), 3)]);
+        let mut report = ReconcileReport {
+            dry_run: false,
+            branches: branch_states(&desired, &existing, false),
+        };
+        report.branches[1].error = Some("rebase conflict".to_string());
+
+        assert_eq!(report.failures(), 1);
+        assert_eq!(report.branches[0].error(), None);
+        assert!(report
+            .to_string()
+            .contains("upgrade/sway-master       rebase      failed: rebase conflict"));
+        assert!(serde_json::to_string(&report)
+            .unwrap()
+            .contains(r#""action":"rebase","behind":3,"error":"rebase conflict""#));
+    }
+
+    #[test]
+    fn only_orphan_branches_created_by_releasy() {
+        let handler_email = "releasy@fuel.sh";
+        // A branch pushed by a person or another bot.
+        assert!(!created_by_releasy(
+            "49699333+dependabot[bot]@users.noreply.github.com\n",
+            handler_email
+        ));
+        assert!(created_by_releasy("releasy@fuel.sh\n", handler_email));
+        // A releasy commit authored with a custom handler email.
+        assert!(created_by_releasy(
+            "ci@fuel.sh\nFuelLabs/fuel-core@337d0eaa\n",
+            handler_email
+        ));
+    }
+}
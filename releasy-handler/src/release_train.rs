@@ -0,0 +1,673 @@
+//! Coordinated releases of several repos of the plan, in dependency order.
+//!
+//! A release train reads a spec listing the repos to release with their new versions. Repos are
+//! released group by group in the order given by `Plan::release_order`, a group only starting
+//! once the previous one is released. For each repo of a group:
+//!
+//!  1. a pull request bumping the version is opened on the default branch of the repo,
+//!  2. the train waits for the pull request to be merged,
+//!  3. and for the release tag to be pushed,
+//!  4. a `new-release` event is emitted to the downstream repos.
+//!
+//! Progress is saved to a state file after every step, so that an interrupted train resumes
+//! where it stopped.
+use std::{
+    collections::{BTreeSet, HashMap},
+    fmt::Display,
+    path::{Path, PathBuf},
+    process::Command,
+    time::Duration,
+};
+
+use releasy_core::{
+    event::{ClientPayload, Event, EventDetails, EventType},
+    pulls::{find_pull_request, get_pull_request, NewPullRequest},
+    repo::Repo,
+};
+use releasy_emit::{
+    outbox::{emit_with_outbox, OutboxLocation},
+    target::{resolve_targets, TargetSelection},
+    DEFAULT_CONCURRENCY,
+};
+use releasy_graph::{manifest::Manifest, plan::Plan};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    handle::{block_on, commit_changes, find_repo, with_repo, ReleasyHandlerCommand, Workspace},
+    version::bump_version,
+};
+
+/// Default location of the state file, relative to the current dir.
+pub const DEFAULT_STATE_FILE_NAME: &str = ".releasy-release-train.json";
+
+/// The repos released by a release train:
+///
+/// ```toml
+/// [[release]]
+/// repo = "fuel-core"
+/// version = "0.21.0"
+///
+/// [[release]]
+/// repo = "FuelLabs/fuels-rs"
+/// version = "0.50.0"
+/// tag = "v0.50.0"
+/// bump-command = "./scripts/bump-version.sh"
+/// ```
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct ReleaseSpec {
+    #[serde(rename = "release")]
+    releases: Vec<RepoRelease>,
+}
+
+impl ReleaseSpec {
+    pub fn from_file(path: &Path) -> anyhow::Result<Self> {
+        let contents = std::fs::read_to_string(path).map_err(|e| {
+            anyhow::anyhow!("failed to read release spec at `{}`: {e}", path.display())
+        })?;
+        Ok(toml::from_str(&contents)?)
+    }
+
+    pub fn releases(&self) -> impl Iterator<Item = &RepoRelease> {
+        self.releases.iter()
+    }
+}
+
+/// A repo released by a release train.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub struct RepoRelease {
+    /// Name of the repo, or `<owner>/<name>` if the name is ambiguous.
+    repo: String,
+    /// Version the repo is bumped to.
+    version: String,
+    /// Tag of the release, `v<version>` by default.
+    tag: Option<String>,
+    /// Shell command bumping the version in a checkout of the repo, run with `RELEASY_VERSION`.
+    /// Cargo workspaces are bumped without a command.
+    bump_command: Option<String>,
+}
+
+impl RepoRelease {
+    pub fn version(&self) -> &str {
+        &self.version
+    }
+
+    /// Returns the tag the release is waiting for.
+    pub fn tag(&self) -> String {
+        self.tag
+            .clone()
+            .unwrap_or_else(|| format!("v{}", self.version))
+    }
+
+    /// Returns the repo of the manifest the release refers to.
+    fn resolve<'a>(&self, manifest: &'a Manifest) -> anyhow::Result<&'a Repo> {
+        find_repo(manifest, &self.repo)
+    }
+}
+
+/// How far the release of a repo went.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+#[serde(tag = "stage", rename_all = "kebab-case")]
+pub enum ReleaseStage {
+    /// Nothing is done yet.
+    Pending,
+    /// The version bump is waiting to be merged.
+    PullRequestOpened { number: u64, url: String },
+    /// The version bump is merged, or the repo was already at the version, and the release tag is
+    /// waiting to be pushed.
+    Merged {
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        pull_request: Option<u64>,
+    },
+    /// The release is tagged and downstream repos are waiting to be notified.
+    Tagged { commit: String },
+    /// Downstream repos are notified of the release.
+    Released,
+}
+
+/// Progress of the release of a repo.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct RepoReleaseState {
+    repo: Repo,
+    version: String,
+    tag: String,
+    /// Index of the group of the repo in the release order.
+    group: usize,
+    #[serde(flatten)]
+    stage: ReleaseStage,
+}
+
+impl RepoReleaseState {
+    pub fn repo(&self) -> &Repo {
+        &self.repo
+    }
+
+    pub fn stage(&self) -> &ReleaseStage {
+        &self.stage
+    }
+
+    /// Returns the branch the version bump is pushed to.
+    fn bump_branch(&self) -> String {
+        format!("release-train/{}", self.tag)
+    }
+}
+
+/// Progress of a release train, in release order.
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, Eq)]
+pub struct ReleaseTrainState {
+    releases: Vec<RepoReleaseState>,
+}
+
+impl ReleaseTrainState {
+    /// Loads the state, which is empty if the train never started.
+    pub fn load(path: &Path) -> anyhow::Result<Self> {
+        match std::fs::read_to_string(path) {
+            Ok(contents) => Ok(serde_json::from_str(&contents)?),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Saves the state, replacing the previously saved one.
+    pub fn save(&self, path: &Path) -> anyhow::Result<()> {
+        if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(path, serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+
+    /// Returns an iterator over the progress of each repo, in release order.
+    pub fn releases(&self) -> impl Iterator<Item = &RepoReleaseState> {
+        self.releases.iter()
+    }
+
+    /// Returns true once every repo is released.
+    pub fn is_done(&self) -> bool {
+        self.releases
+            .iter()
+            .all(|release| release.stage == ReleaseStage::Released)
+    }
+
+    /// Returns the state of the train described by the spec, keeping the progress recorded in
+    /// this state. Fails if the recorded progress is for other versions.
+    fn resume(self, spec: &ReleaseSpec, manifest: &Manifest) -> anyhow::Result<Self> {
+        let plan = Plan::try_from_manifest(manifest.clone())?;
+        let mut planned = HashMap::new();
+        for release in spec.releases() {
+            let repo = release.resolve(manifest)?;
+            if planned.insert(repo.clone(), release).is_some() {
+                anyhow::bail!("{repo} is released more than once");
+            }
+        }
+        let mut recorded = self
+            .releases
+            .into_iter()
+            .map(|release| (release.repo.clone(), release))
+            .collect::<HashMap<_, _>>();
+
+        let mut releases = vec![];
+        let groups = plan
+            .release_order()
+            .into_iter()
+            .map(|group| {
+                group
+                    .into_iter()
+                    .filter(|repo| planned.contains_key(*repo))
+                    .collect::<Vec<_>>()
+            })
+            .filter(|group| !group.is_empty());
+        for (group_ix, group) in groups.enumerate() {
+            for repo in group {
+                let release = planned[repo];
+                let stage = match recorded.remove(repo) {
+                    Some(state) if state.version != release.version() => anyhow::bail!(
+                        "the state file records the release of {} {} instead of {}, remove it to \
+                         start a new release train",
+                        repo,
+                        state.version,
+                        release.version()
+                    ),
+                    Some(state) => state.stage,
+                    None => ReleaseStage::Pending,
+                };
+                releases.push(RepoReleaseState {
+                    repo: repo.clone(),
+                    version: release.version().to_string(),
+                    tag: release.tag(),
+                    group: group_ix,
+                    stage,
+                });
+            }
+        }
+        Ok(Self { releases })
+    }
+}
+
+impl Display for ReleaseTrainState {
+    /// Formats the state as a table.
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let repo_name = |repo: &Repo| format!("{}/{}", repo.owner(), repo.name());
+        let repo_width = self
+            .releases
+            .iter()
+            .map(|release| repo_name(&release.repo).len())
+            .chain(std::iter::once("REPO".len()))
+            .max()
+            .unwrap_or_default();
+        writeln!(
+            f,
+            "{:5}  {:repo_width$}  {:10}  STAGE",
+            "GROUP", "REPO", "TAG"
+        )?;
+        for release in &self.releases {
+            let stage = match &release.stage {
+                ReleaseStage::Pending => "pending".to_string(),
+                ReleaseStage::PullRequestOpened { url, .. } => format!("waiting for {url}"),
+                ReleaseStage::Merged { .. } => "waiting for the tag".to_string(),
+                ReleaseStage::Tagged { commit } => format!("tagged at {commit}"),
+                ReleaseStage::Released => "released".to_string(),
+            };
+            writeln!(
+                f,
+                "{:5}  {:repo_width$}  {:10}  {stage}",
+                release.group + 1,
+                repo_name(&release.repo),
+                release.tag
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Flags controlling how a release train runs.
+#[derive(Clone, Debug)]
+pub struct ReleaseTrainOptions {
+    /// Where the progress of the train is saved.
+    pub state_path: PathBuf,
+    /// How long to wait between checks of pull requests and tags.
+    pub poll_interval: Duration,
+    /// Stop once nothing can be done without waiting, instead of polling until the train is done.
+    pub once: bool,
+    /// Only report the release order and the recorded progress, without doing anything.
+    pub dry_run: bool,
+}
+
+/// Runs the release train described by the spec, resuming from the saved state, and returns the
+/// state it stopped at.
+pub fn release_train(
+    manifest: Manifest,
+    spec: &ReleaseSpec,
+    options: &ReleaseTrainOptions,
+) -> anyhow::Result<ReleaseTrainState> {
+    let mut state = ReleaseTrainState::load(&options.state_path)?.resume(spec, &manifest)?;
+    if options.dry_run {
+        return Ok(state);
+    }
+    state.save(&options.state_path)?;
+    let bump_commands = spec
+        .releases()
+        .map(|release| {
+            Ok((
+                release.resolve(&manifest)?.clone(),
+                release.bump_command.clone(),
+            ))
+        })
+        .collect::<anyhow::Result<HashMap<_, _>>>()?;
+    let groups = state
+        .releases
+        .iter()
+        .map(|release| release.group)
+        .collect::<BTreeSet<_>>();
+
+    for group in groups {
+        loop {
+            let mut done = true;
+            for index in 0..state.releases.len() {
+                if state.releases[index].group != group {
+                    continue;
+                }
+                let bump_command = bump_commands[&state.releases[index].repo].as_deref();
+                // Steps are taken until the release has to wait.
+                while let Some(next_stage) =
+                    advance(&state.releases[index], bump_command, &manifest)?
+                {
+                    state.releases[index].stage = next_stage;
+                    state.save(&options.state_path)?;
+                }
+                done &= state.releases[index].stage == ReleaseStage::Released;
+            }
+            if done {
+                break;
+            }
+            if options.once {
+                return Ok(state);
+            }
+            std::thread::sleep(options.poll_interval);
+        }
+    }
+    Ok(state)
+}
+
+/// Takes the next step of the release of a repo, and returns the stage it reached if it moved
+/// forward. Returns `None` if the release has to wait, or is done.
+fn advance(
+    release: &RepoReleaseState,
+    bump_command: Option<&str>,
+    manifest: &Manifest,
+) -> anyhow::Result<Option<ReleaseStage>> {
+    let repo = &release.repo;
+    let settings = manifest.settings(repo);
+    match &release.stage {
+        ReleaseStage::Pending => {
+            let github_token = dispatch_token()?;
+            let branch = release.bump_branch();
+            // A pull request opened by an interrupted run is reused.
+            let existing = {
+                let (repo, branch, github_token) =
+                    (repo.clone(), branch.clone(), github_token.clone());
+                block_on(
+                    async move { Ok(find_pull_request(&repo, &branch, &github_token).await?) },
+                )?
+            };
+            if let Some(pull_request) = existing.filter(|pr| !pr.is_abandoned()) {
+                println!("Found {} bumping {repo}", pull_request.url());
+                return Ok(Some(ReleaseStage::PullRequestOpened {
+                    number: pull_request.number(),
+                    url: pull_request.url().to_string(),
+                }));
+            }
+
+            let mut default_branch = None;
+            let mut bumped = false;
+            with_repo(
+                &format!("release-train-{}", repo.name()),
+                repo,
+                &settings,
+                &Workspace::Temporary,
+                |repo_path, branch_name| {
+                    default_branch = Some(branch_name.to_string());
+                    ReleasyHandlerCommand::new("git")
+                        .arg("checkout")
+                        .arg("-B")
+                        .arg(&branch)
+                        .current_dir(repo_path)
+                        .execute()?;
+                    match bump_command {
+                        Some(bump_command) => ReleasyHandlerCommand::new("sh")
+                            .arg("-c")
+                            .arg(bump_command)
+                            .env("RELEASY_VERSION", &release.version)
+                            .current_dir(repo_path)
+                            .execute()?,
+                        None => {
+                            let previous_version =
+                                bump_version(&release.version, &settings.ecosystem(), repo_path)?;
+                            println!(
+                                "Bumping {repo} from {previous_version} to {}",
+                                release.version
+                            );
+                        }
+                    }
+                    bumped =
+                        commit_changes(&format!("Bump version to {}", release.version), repo_path)?;
+                    if bumped {
+                        ReleasyHandlerCommand::new("git")
+                            .arg("push")
+                            .arg(format!("--force-with-lease={branch}"))
+                            .arg("origin")
+                            .arg(&branch)
+                            .current_dir(repo_path)
+                            .execute()?;
+                    }
+                    Ok(())
+                },
+            )?;
+            if !bumped {
+                println!("{repo} is already at {}", release.version);
+                return Ok(Some(ReleaseStage::Merged { pull_request: None }));
+            }
+
+            let pull_request = NewPullRequest::new(
+                format!("Bump version to {}", release.version),
+                branch,
+                default_branch.unwrap_or_default(),
+            )
+            .with_body(Some(format!(
+                "Opened by the releasy release train. Once merged, the release is expected to be \
+                 tagged as `{}`.",
+                release.tag
+            )));
+            let repo = repo.clone();
+            let pull_request =
+                block_on(async move { Ok(pull_request.open(&repo, &github_token).await?) })?;
+            println!("Opened {}", pull_request.url());
+            Ok(Some(ReleaseStage::PullRequestOpened {
+                number: pull_request.number(),
+                url: pull_request.url().to_string(),
+            }))
+        }
+        ReleaseStage::PullRequestOpened { number, url } => {
+            let github_token = dispatch_token()?;
+            let (repo, number) = (repo.clone(), *number);
+            let pull_request =
+                block_on(async move { Ok(get_pull_request(&repo, number, &github_token).await?) })?;
+            if pull_request.is_abandoned() {
+                anyhow::bail!(
+                    "{url} is closed without being merged, reopen it or remove {} from the \
+                     release spec",
+                    release.repo
+                );
+            }
+            if !pull_request.is_merged() {
+                println!("Waiting for {url} to be merged");
+                return Ok(None);
+            }
+            println!("{url} is merged");
+            Ok(Some(ReleaseStage::Merged {
+                pull_request: Some(number),
+            }))
+        }
+        ReleaseStage::Merged { .. } => {
+            let repo_url = match settings.clone_url() {
+                Some(clone_url) => clone_url.to_string(),
+                None => repo.github_url()?,
+            };
+            match tag_commit(repo, &repo_url, &release.tag)? {
+                Some(commit) => {
+                    println!("{repo} is tagged as {} at {commit}", release.tag);
+                    Ok(Some(ReleaseStage::Tagged { commit }))
+                }
+                None => {
+                    println!("Waiting for {repo} to be tagged as {}", release.tag);
+                    Ok(None)
+                }
+            }
+        }
+        ReleaseStage::Tagged { commit } => {
+            emit_release(repo, commit, &release.tag, manifest)?;
+            Ok(Some(ReleaseStage::Released))
+        }
+        ReleaseStage::Released => Ok(None),
+    }
+}
+
+/// Emits a `new-release` event on behalf of the released repo to its downstream repos.
+///
+/// Undelivered events are kept in the default outbox, so that they can be redelivered with
+/// `releasy replay`.
+fn emit_release(repo: &Repo, commit: &str, tag: &str, manifest: &Manifest) -> anyhow::Result<()> {
+    let details = EventDetails::new(Some(commit.to_string()), Some(tag.to_string()));
+    let event = Event::new(
+        EventType::NewRelease,
+        ClientPayload::new(repo.clone(), details),
+    );
+    let plan = Plan::try_from_manifest(manifest.clone())?;
+    let selection = TargetSelection::new(vec![], vec![], false);
+    let targets = resolve_targets(&event, &plan, repo, &selection)?
+        .into_iter()
+        .map(|target| {
+            let delivery = manifest.settings(target.repo()).delivery();
+            target.with_delivery(delivery)
+        })
+        .collect();
+    let report = block_on(async move {
+        emit_with_outbox(
+            &event,
+            targets,
+            DEFAULT_CONCURRENCY,
+            &OutboxLocation::default(),
+        )
+        .await
+    })?;
+    println!("{report}");
+    if report.failed() > 0 {
+        println!("WARNING: undelivered events are kept in the outbox, run `releasy replay`");
+    }
+    Ok(())
+}
+
+/// Returns the commit the tag points to on the remote, if the tag exists.
+///
+/// Errors name the repo rather than its URL, which may hold a token.
+fn tag_commit(repo: &Repo, repo_url: &str, tag: &str) -> anyhow::Result<Option<String>> {
+    let tag_ref = format!("refs/tags/{tag}");
+    // Annotated tags are listed with the commit they point to as `<tag>^{}`.
+    let output = Command::new("git")
+        .arg("ls-remote")
+        .arg("--tags")
+        .arg(repo_url)
+        .arg(&tag_ref)
+        .arg(format!("{tag_ref}^{{}}"))
+        .output()?;
+    if !output.status.success() {
+        anyhow::bail!("cannot list the tags of {}/{}", repo.owner(), repo.name());
+    }
+    Ok(parse_tag_commit(
+        &String::from_utf8_lossy(&output.stdout),
+        &tag_ref,
+    ))
+}
+
+fn parse_tag_commit(ls_remote: &str, tag_ref: &str) -> Option<String> {
+    let refs = ls_remote
+        .lines()
+        .filter_map(|line| line.split_once('\t'))
+        .collect::<Vec<_>>();
+    let peeled = format!("{tag_ref}^{{}}");
+    refs.iter()
+        .find(|(_, name)| *name == peeled)
+        .or_else(|| refs.iter().find(|(_, name)| *name == tag_ref))
+        .map(|(commit, _)| commit.to_string())
+}
+
+fn dispatch_token() -> anyhow::Result<String> {
+    std::env::var("DISPATCH_TOKEN").map_err(|_| {
+        anyhow::anyhow!("DISPATCH_TOKEN should be set to open and check pull requests")
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_tag_commit, ReleaseSpec, ReleaseStage, ReleaseTrainState};
+    use releasy_graph::manifest::ManifestFile;
+
+    const MANIFEST: &str = r#"
+[current-repo]
+name = "sway"
+owner = "FuelLabs"
+
+[repo.fuel-core.details]
+name = "fuel-core"
+owner = "FuelLabs"
+
+[repo.fuels-rs.details]
+name = "fuels-rs"
+owner = "FuelLabs"
+
+[repo.fuels-rs]
+dependencies = ["fuel-core", "sway"]
+
+[repo.sway.details]
+name = "sway"
+owner = "FuelLabs"
+
+[repo.sway]
+dependencies = ["fuels-rs"]
+
+[repo.forc-wallet.details]
+name = "forc-wallet"
+owner = "FuelLabs"
+
+[repo.forc-wallet]
+dependencies = ["fuels-rs"]
+"#;
+
+    #[test]
+    fn resume_release_train() {
+        let manifest = ManifestFile::try_from(MANIFEST.to_string())
+            .unwrap()
+            .manifest();
+        let spec: ReleaseSpec = toml::from_str(
+            r#"
+[[release]]
+repo = "sway"
+version = "0.47.0"
+
+[[release]]
+repo = "FuelLabs/fuel-core"
+version = "0.21.0"
+
+[[release]]
+repo = "fuels-rs"
+version = "0.50.0"
+tag = "fuels-v0.50.0"
+"#,
+        )
+        .unwrap();
+
+        let state = ReleaseTrainState::default()
+            .resume(&spec, &manifest)
+            .unwrap();
+        let order = state
+            .releases()
+            .map(|release| (release.group, release.repo().name(), release.tag.as_str()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            order,
+            vec![
+                (0, "fuel-core", "v0.21.0"),
+                (1, "fuels-rs", "fuels-v0.50.0"),
+                (1, "sway", "v0.47.0"),
+            ]
+        );
+
+        let mut recorded = state.clone();
+        recorded.releases[0].stage = ReleaseStage::Released;
+        let json = serde_json::to_string(&recorded).unwrap();
+        assert!(json.contains(r#""stage":"released""#));
+        let resumed = serde_json::from_str::<ReleaseTrainState>(&json)
+            .unwrap()
+            .resume(&spec, &manifest)
+            .unwrap();
+        assert_eq!(resumed, recorded);
+
+        recorded.releases[1].version = "0.49.0".to_string();
+        assert!(recorded.resume(&spec, &manifest).is_err());
+    }
+
+    #[test]
+    fn parse_tag_commits() {
+        let lightweight = "9e3b1c7a\trefs/tags/v0.21.0\n";
+        assert_eq!(
+            parse_tag_commit(lightweight, "refs/tags/v0.21.0").as_deref(),
+            Some("9e3b1c7a")
+        );
+        let annotated = "e1f2a3b4\trefs/tags/v0.21.0\n9e3b1c7a\trefs/tags/v0.21.0^{}\n";
+        assert_eq!(
+            parse_tag_commit(annotated, "refs/tags/v0.21.0").as_deref(),
+            Some("9e3b1c7a")
+        );
+        assert_eq!(parse_tag_commit("", "refs/tags/v0.21.0"), None);
+    }
+}
@@ -0,0 +1,744 @@
+//! A long running server handling events delivered by GitHub webhooks.
+//!
+//! Deliveries are verified with the `X-Hub-Signature-256` header, converted into events for the
+//! served repos and queued per repo. Events of a repo are handled one at a time, in a workspace
+//! that is kept between events.
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    convert::Infallible,
+    net::SocketAddr,
+    path::PathBuf,
+    sync::Arc,
+};
+
+use hmac::{Hmac, Mac};
+use hyper::{
+    body::HttpBody,
+    header::CONTENT_LENGTH,
+    service::{make_service_fn, service_fn},
+    Body, Method, Request, Response, StatusCode,
+};
+use releasy_core::{
+    event::{ClientPayload, Event, EventDetails, EventType, PullRequest},
+    repo::Repo,
+};
+use releasy_graph::{manifest::Manifest, plan::Plan};
+use serde::Deserialize;
+use sha2::Sha256;
+use tokio::sync::mpsc;
+
+use crate::handle::{EventHandler, Workspace};
+
+/// Maximum size of a delivery body, GitHub caps webhook payloads at 25 MB.
+const MAX_BODY_SIZE: usize = 25 * 1024 * 1024;
+
+/// Configuration of the webhook server.
+#[derive(Clone, Debug)]
+pub struct ServeConfig {
+    /// Address the server listens on.
+    pub addr: SocketAddr,
+    /// Secret of the webhooks, used to verify the signature of deliveries.
+    pub secret: String,
+    /// Manifests of the served repos, one per current repo.
+    pub manifests: Vec<Manifest>,
+    /// Directory keeping the checkouts of the served repos between events.
+    pub workspace_dir: PathBuf,
+    /// Maximum number of events waiting to be handled per repo. Deliveries are rejected once the
+    /// queue of a repo is full, so that GitHub reports them as failed and they can be redelivered.
+    pub queue_capacity: usize,
+}
+
+/// A repo handled by the server.
+struct ServedRepo {
+    repo: Repo,
+    /// Repos the served repo depends on directly, with their branches tracked by the served repo.
+    upstream: BTreeMap<Repo, BTreeSet<String>>,
+    /// Branches of the served repo, besides the default branch, that tracking branches are based
+    /// on.
+    base_branches: BTreeSet<String>,
+    queue: mpsc::Sender<Event>,
+}
+
+/// Runs the webhook server until it fails.
+pub async fn serve(config: ServeConfig) -> anyhow::Result<()> {
+    let mut served_repos = vec![];
+    for manifest in config.manifests {
+        let repo = manifest.current_repo().clone();
+        let plan = Plan::try_from_manifest(manifest.clone())?;
+        let upstream = tracked_upstream_branches(&plan, &repo)?;
+        let mut base_branches = manifest
+            .settings(&repo)
+            .release_branches()
+            .cloned()
+            .collect::<BTreeSet<_>>();
+        for (upstream, edge) in plan.upstream_edges(repo.clone())? {
+            base_branches.extend(
+                edge.tracking_bases(upstream, plan.default_branch(upstream))
+                    .into_iter()
+                    .filter_map(|(_, _, base_branch)| base_branch),
+            );
+        }
+
+        let (queue, events) = mpsc::channel(config.queue_capacity.max(1));
+        let workspace = Workspace::Cached(config.workspace_dir.clone());
+        tokio::spawn(handle_queue(events, manifest, workspace));
+        served_repos.push(ServedRepo {
+            repo,
+            upstream,
+            base_branches,
+            queue,
+        });
+    }
+
+    let served_repos = Arc::new(served_repos);
+    let secret = Arc::new(config.secret);
+    let make_service = make_service_fn(move |_| {
+        let served_repos = served_repos.clone();
+        let secret = secret.clone();
+        async move {
+            Ok::<_, Infallible>(service_fn(move |request| {
+                let served_repos = served_repos.clone();
+                let secret = secret.clone();
+                async move {
+                    let response = handle_delivery(request, &secret, &served_repos).await;
+                    Ok::<_, Infallible>(response)
+                }
+            }))
+        }
+    });
+
+    println!("Listening for webhooks on {}", config.addr);
+    hyper::Server::try_bind(&config.addr)?
+        .serve(make_service)
+        .await?;
+    Ok(())
+}
+
+/// Returns the repos the given repo depends on directly, with the branches of them it tracks.
+///
+/// Like `emit`, only immediate dependencies are notified of upstream changes.
+fn tracked_upstream_branches(
+    plan: &Plan,
+    repo: &Repo,
+) -> anyhow::Result<BTreeMap<Repo, BTreeSet<String>>> {
+    let upstream = plan
+        .upstream_edges(repo.clone())?
+        .map(|(upstream, edge)| {
+            let tracked_branches = edge
+                .branch_mapping(upstream, plan.default_branch(upstream))
+                .into_iter()
+                .map(|(upstream_branch, _)| upstream_branch)
+                .collect();
+            (upstream.clone(), tracked_branches)
+        })
+        .collect();
+    Ok(upstream)
+}
+
+/// Handles the queued events of a repo one at a time.
+async fn handle_queue(mut events: mpsc::Receiver<Event>, manifest: Manifest, workspace: Workspace) {
+    while let Some(event) = events.recv().await {
+        let manifest = manifest.clone();
+        let workspace = workspace.clone();
+        let current_repo = manifest.current_repo().clone();
+        let handled =
+            tokio::task::spawn_blocking(move || event.handle_in(manifest, &workspace)).await;
+        match handled {
+            Ok(Ok(())) => {}
+            Ok(Err(e)) => println!("ERROR: failed to handle event for {current_repo}: {e}"),
+            Err(e) => println!("ERROR: handler for {current_repo} panicked: {e}"),
+        }
+    }
+}
+
+async fn handle_delivery(
+    request: Request<Body>,
+    secret: &str,
+    served_repos: &[ServedRepo],
+) -> Response<Body> {
+    if request.method() != Method::POST {
+        return response(StatusCode::METHOD_NOT_ALLOWED, "expected a POST request");
+    }
+    let header = |name: &str| {
+        request
+            .headers()
+            .get(name)
+            .and_then(|value| value.to_str().ok())
+            .map(str::to_string)
+    };
+    let (Some(github_event), Some(signature)) =
+        (header("X-GitHub-Event"), header("X-Hub-Signature-256"))
+    else {
+        return response(StatusCode::BAD_REQUEST, "missing GitHub headers");
+    };
+    let content_length = request
+        .headers()
+        .get(CONTENT_LENGTH)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.parse::<usize>().ok());
+    if content_length.is_some_and(|length| length > MAX_BODY_SIZE) {
+        return response(StatusCode::PAYLOAD_TOO_LARGE, "body is too large");
+    }
+    let body = match read_body(request.into_body(), MAX_BODY_SIZE).await {
+        Ok(Some(body)) => body,
+        Ok(None) => return response(StatusCode::PAYLOAD_TOO_LARGE, "body is too large"),
+        Err(_) => return response(StatusCode::BAD_REQUEST, "failed to read the body"),
+    };
+    if !verify_signature(secret.as_bytes(), &body, &signature) {
+        return response(StatusCode::UNAUTHORIZED, "invalid signature");
+    }
+    let events = match events_from_delivery(&github_event, &body, served_repos) {
+        Ok(events) => events,
+        Err(e) => return response(StatusCode::BAD_REQUEST, &e.to_string()),
+    };
+
+    let mut rejected = vec![];
+    let accepted = events.len();
+    for (served_repo, event) in events {
+        println!(
+            "Queueing `{}` event from {} for {}",
+            event.event_type(),
+            event.client_payload().repo(),
+            served_repo.repo
+        );
+        if served_repo.queue.try_send(event).is_err() {
+            rejected.push(served_repo.repo.to_string());
+        }
+    }
+    if rejected.is_empty() {
+        response(StatusCode::ACCEPTED, &format!("queued {accepted} events"))
+    } else {
+        response(
+            StatusCode::SERVICE_UNAVAILABLE,
+            &format!("queues are full for: {}", rejected.join(", ")),
+        )
+    }
+}
+
+/// Reads the body, returning None as soon as it grows past `limit` bytes.
+async fn read_body(mut body: Body, limit: usize) -> Result<Option<Vec<u8>>, hyper::Error> {
+    let mut bytes = vec![];
+    while let Some(chunk) = body.data().await {
+        let chunk = chunk?;
+        if bytes.len() + chunk.len() > limit {
+            return Ok(None);
+        }
+        bytes.extend_from_slice(&chunk);
+    }
+    Ok(Some(bytes))
+}
+
+fn response(status: StatusCode, message: &str) -> Response<Body> {
+    let mut response = Response::new(Body::from(message.to_string()));
+    *response.status_mut() = status;
+    response
+}
+
+/// Returns true if `signature` is the `sha256=<hex>` HMAC of the body with the given secret.
+fn verify_signature(secret: &[u8], body: &[u8], signature: &str) -> bool {
+    let Some(signature) = signature
+        .strip_prefix("sha256=")
+        .and_then(|signature| hex::decode(signature).ok())
+    else {
+        return false;
+    };
+    let Ok(mut mac) = Hmac::<Sha256>::new_from_slice(secret) else {
+        return false;
+    };
+    mac.update(body);
+    mac.verify_slice(&signature).is_ok()
+}
+
+#[derive(Deserialize)]
+struct WebhookRepository {
+    full_name: String,
+    default_branch: Option<String>,
+}
+
+impl WebhookRepository {
+    fn repo(&self) -> anyhow::Result<Repo> {
+        let (owner, name) = self
+            .full_name
+            .split_once('/')
+            .ok_or_else(|| anyhow::anyhow!("invalid repository name `{}`", self.full_name))?;
+        Ok(Repo::new(name.to_string(), owner.to_string()))
+    }
+}
+
+#[derive(Deserialize)]
+struct PushPayload {
+    #[serde(rename = "ref")]
+    git_ref: String,
+    before: String,
+    after: String,
+    #[serde(default)]
+    deleted: bool,
+    head_commit: Option<PushCommit>,
+    #[serde(default)]
+    commits: Vec<PushCommit>,
+    repository: WebhookRepository,
+}
+
+#[derive(Deserialize)]
+struct PushCommit {
+    message: String,
+    timestamp: String,
+    author: PushCommitAuthor,
+    #[serde(default)]
+    added: Vec<String>,
+    #[serde(default)]
+    removed: Vec<String>,
+    #[serde(default)]
+    modified: Vec<String>,
+}
+
+#[derive(Deserialize)]
+struct PushCommitAuthor {
+    name: String,
+    email: String,
+}
+
+#[derive(Deserialize)]
+struct ReleasePayload {
+    action: String,
+    release: Release,
+    repository: WebhookRepository,
+}
+
+#[derive(Deserialize)]
+struct Release {
+    tag_name: String,
+    target_commitish: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct PullRequestPayload {
+    action: String,
+    pull_request: WebhookPullRequest,
+    repository: WebhookRepository,
+}
+
+#[derive(Deserialize)]
+struct WebhookPullRequest {
+    number: u64,
+    title: String,
+    head: PullRequestHead,
+}
+
+#[derive(Deserialize)]
+struct PullRequestHead {
+    sha: String,
+}
+
+#[derive(Deserialize)]
+struct RepositoryDispatchPayload {
+    action: String,
+    client_payload: ClientPayload,
+    repository: WebhookRepository,
+}
+
+/// Maximum number of commits GitHub lists in push deliveries. Changed paths of larger pushes are
+/// not complete, nor are the ones of pushes listing no commits, e.g. force pushes to older commits.
+const MAX_PUSH_COMMITS: usize = 20;
+
+/// Converts a webhook delivery into the events to be handled by the served repos.
+///
+/// - `push` to the default branch, or to a release or base branch, of a served repo is a
+///   `new-commit-to-self` event for it, and a push to a branch tracked by the served repos
+///   depending directly on the pushed repo is a `new-commit-to-dependency` event for them.
+/// - `release` is a `new-release` event for the served repos depending on the released repo.
+/// - `pull_request` is a `new-pull-request` event for the served repos depending on the repo of
+///   the pull request, when it is opened, updated or closed.
+/// - `repository_dispatch` is delivered as is to the served repo receiving it.
+///
+/// Other deliveries, e.g. `ping`, produce no events.
+fn events_from_delivery<'a>(
+    github_event: &str,
+    body: &[u8],
+    served_repos: &'a [ServedRepo],
+) -> anyhow::Result<Vec<(&'a ServedRepo, Event)>> {
+    let mut events = vec![];
+    match github_event {
+        "push" => {
+            let push: PushPayload = serde_json::from_slice(body)?;
+            let Some(branch) = push.git_ref.strip_prefix("refs/heads/") else {
+                return Ok(events);
+            };
+            if push.deleted {
+                return Ok(events);
+            }
+            let source_repo = push.repository.repo()?;
+            let details = push_details(&push, branch);
+            for served_repo in served_repos {
+                let event_type = if served_repo.repo == source_repo {
+                    if push.repository.default_branch.as_deref() != Some(branch)
+                        && !served_repo.base_branches.contains(branch)
+                    {
+                        continue;
+                    }
+                    EventType::NewCommitToSelf
+                } else if served_repo
+                    .upstream
+                    .get(&source_repo)
+                    .is_some_and(|tracked_branches| tracked_branches.contains(branch))
+                {
+                    EventType::NewCommitToDependency
+                } else {
+                    continue;
+                };
+                let client_payload = ClientPayload::new(source_repo.clone(), details.clone());
+                events.push((served_repo, Event::new(event_type, client_payload)));
+            }
+        }
+        "release" => {
+            let release: ReleasePayload = serde_json::from_slice(body)?;
+            if release.action != "published" {
+                return Ok(events);
+            }
+            let source_repo = release.repository.repo()?;
+            let details = EventDetails::new(None, Some(release.release.tag_name))
+                .with_branch(release.release.target_commitish);
+            for served_repo in served_repos {
+                if served_repo.upstream.contains_key(&source_repo) {
+                    let client_payload = ClientPayload::new(source_repo.clone(), details.clone());
+                    events.push((
+                        served_repo,
+                        Event::new(EventType::NewRelease, client_payload),
+                    ));
+                }
+            }
+        }
+        "pull_request" => {
+            let payload: PullRequestPayload = serde_json::from_slice(body)?;
+            let closed = match payload.action.as_str() {
+                "opened" | "reopened" | "synchronize" => false,
+                "closed" => true,
+                _ => return Ok(events),
+            };
+            let source_repo = payload.repository.repo()?;
+            let pull_request = payload.pull_request;
+            let details = EventDetails::new(Some(pull_request.head.sha), None)
+                .with_subject(Some(pull_request.title))
+                .with_pull_request(Some(
+                    PullRequest::new(pull_request.number).with_closed(closed),
+                ));
+            for served_repo in served_repos {
+                if served_repo.upstream.contains_key(&source_repo) {
+                    let client_payload = ClientPayload::new(source_repo.clone(), details.clone());
+                    events.push((
+                        served_repo,
+                        Event::new(EventType::NewPullRequest, client_payload),
+                    ));
+                }
+            }
+        }
+        "repository_dispatch" => {
+            let dispatch: RepositoryDispatchPayload = serde_json::from_slice(body)?;
+            let target_repo = dispatch.repository.repo()?;
+            let event_type = dispatch.action.parse::<EventType>()?;
+            if let Some(served_repo) = served_repos.iter().find(|r| r.repo == target_repo) {
+                events.push((served_repo, Event::new(event_type, dispatch.client_payload)));
+            }
+        }
+        _ => {}
+    }
+    Ok(events)
+}
+
+/// Returns the event details describing a push.
+fn push_details(push: &PushPayload, branch: &str) -> EventDetails {
+    // A push to a new branch has no previous head.
+    let base_commit_hash =
+        Some(push.before.clone()).filter(|before| before.chars().any(|c| c != '0'));
+    let changed_paths = (1..MAX_PUSH_COMMITS)
+        .contains(&push.commits.len())
+        .then(|| {
+            push.commits
+                .iter()
+                .flat_map(|commit| [&commit.added, &commit.removed, &commit.modified])
+                .flatten()
+                .cloned()
+                .collect::<BTreeSet<_>>()
+                .into_iter()
+                .collect()
+        });
+    let details = EventDetails::new(Some(push.after.clone()), None)
+        .with_branch(Some(branch.to_string()))
+        .with_base_commit_hash(base_commit_hash)
+        .with_changed_paths(changed_paths);
+    match &push.head_commit {
+        Some(head_commit) => details
+            .with_author(Some(format!(
+                "{} <{}>",
+                head_commit.author.name, head_commit.author.email
+            )))
+            .with_subject(head_commit.message.lines().next().map(str::to_string))
+            .with_timestamp(Some(head_commit.timestamp.clone())),
+        None => details,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        events_from_delivery, read_body, tracked_upstream_branches, verify_signature, ServedRepo,
+    };
+    use crate::handle::block_on;
+    use releasy_core::{event::EventType, repo::Repo};
+    use releasy_graph::{manifest::ManifestFile, plan::Plan};
+    use std::collections::{BTreeMap, BTreeSet};
+    use tokio::sync::mpsc;
+
+    /// Example from the GitHub documentation on validating webhook deliveries.
+    #[test]
+    fn verify_github_signature() {
+        let secret = b"It's a Secret to Everybody";
+        let body = b"Hello, World!";
+        let signature = "sha256=757107ea0eb2509fc211221cce984b8a37570b6d7586c22c46f4379c8b043e17";
+        assert!(verify_signature(secret, body, signature));
+        assert!(!verify_signature(b"wrong secret", body, signature));
+        assert!(!verify_signature(secret, body, "sha1=757107ea"));
+        assert!(!verify_signature(secret, body, "sha256=not-hex"));
+    }
+
+    fn served_repo(name: &str, upstream: &[&str]) -> ServedRepo {
+        let (queue, _) = mpsc::channel(1);
+        ServedRepo {
+            repo: Repo::new(name.to_string(), "FuelLabs".to_string()),
+            upstream: upstream
+                .iter()
+                .map(|name| {
+                    let upstream = Repo::new(name.to_string(), "FuelLabs".to_string());
+                    (upstream, BTreeSet::from(["master".to_string()]))
+                })
+                .collect::<BTreeMap<_, _>>(),
+            base_branches: BTreeSet::from(["release/v0.46".to_string()]),
+            queue,
+        }
+    }
+
+    #[test]
+    fn track_immediate_upstream_branches() {
+        let manifest_str = r#"
+current-repo = { name = "fuels-rs", owner = "FuelLabs" }
+
+[repo.fuel-core.details]
+name = "fuel-core"
+owner = "FuelLabs"
+
+[repo.fuel-core.settings]
+default-branch = "main"
+
+[repo.sway.details]
+name = "sway"
+owner = "FuelLabs"
+
+[repo.sway]
+dependencies = ["fuel-core"]
+
+[repo.fuels-rs.details]
+name = "fuels-rs"
+owner = "FuelLabs"
+
+[repo.fuels-rs]
+dependencies = [
+  { name = "sway", branches = { master = "upgrade/sway-master", "release/v0.46" = "upgrade/sway-v0.46" } },
+]
+"#;
+        let manifest = ManifestFile::try_from(manifest_str.to_string())
+            .unwrap()
+            .manifest();
+        let plan = Plan::try_from_manifest(manifest.clone()).unwrap();
+        let repo = |name: &str| Repo::new(name.to_string(), "FuelLabs".to_string());
+        let branches = |branches: &[&str]| branches.iter().map(|b| b.to_string()).collect();
+        assert_eq!(
+            tracked_upstream_branches(&plan, &repo("fuels-rs")).unwrap(),
+            BTreeMap::from([(repo("sway"), branches(&["master", "release/v0.46"]))])
+        );
+        // Only the default branch of upstreams without a branch mapping is tracked.
+        assert_eq!(
+            tracked_upstream_branches(&plan, &repo("sway")).unwrap(),
+            BTreeMap::from([(repo("fuel-core"), branches(&["main"]))])
+        );
+    }
+
+    #[test]
+    fn read_body_up_to_the_limit() {
+        let body = || hyper::Body::from(vec![0u8; 16]);
+        let read = |limit| block_on(async move { Ok(read_body(body(), limit).await?) }).unwrap();
+        assert_eq!(read(16), Some(vec![0u8; 16]));
+        assert_eq!(read(15), None);
+    }
+
+    #[test]
+    fn convert_push_to_events() {
+        let served_repos = vec![
+            served_repo("fuels-rs", &["fuel-core", "sway"]),
+            served_repo("sway", &["fuel-core"]),
+            served_repo("fuel-core", &[]),
+        ];
+        let push = r#"{
+            "ref": "refs/heads/master",
+            "before": "0000000000000000000000000000000000000000",
+            "after": "337d0eaa130dd18e9e347f83ab4fab76b3a6bd2a",
+            "head_commit": {
+                "message": "Add new opcode\n\nLonger description",
+                "timestamp": "2023-10-10T10:00:00Z",
+                "author": { "name": "dev", "email": "dev@fuel.sh" },
+                "added": ["fuel-vm/src/lib.rs"]
+            },
+            "commits": [
+                {
+                    "message": "Add new opcode",
+                    "timestamp": "2023-10-10T10:00:00Z",
+                    "author": { "name": "dev", "email": "dev@fuel.sh" },
+                    "added": ["fuel-vm/src/lib.rs"],
+                    "modified": ["Cargo.toml"]
+                }
+            ],
+            "repository": { "full_name": "FuelLabs/fuel-core", "default_branch": "master" }
+        }"#;
+
+        let events = events_from_delivery("push", push.as_bytes(), &served_repos).unwrap();
+        let events = events
+            .iter()
+            .map(|(served_repo, event)| (served_repo.repo.name(), event.event_type().clone()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            events,
+            vec![
+                ("fuels-rs", EventType::NewCommitToDependency),
+                ("sway", EventType::NewCommitToDependency),
+                ("fuel-core", EventType::NewCommitToSelf),
+            ]
+        );
+
+        let (_, event) = &events_from_delivery("push", push.as_bytes(), &served_repos).unwrap()[0];
+        let details = event.client_payload().details();
+        assert_eq!(details.subject().unwrap(), "Add new opcode");
+        assert_eq!(details.base_commit_hash(), None);
+        assert_eq!(
+            details.changed_paths().unwrap(),
+            ["Cargo.toml", "fuel-vm/src/lib.rs"]
+        );
+
+        let self_events = |git_ref: &str| {
+            let push = push.replace("refs/heads/master", git_ref);
+            events_from_delivery("push", push.as_bytes(), &served_repos)
+                .unwrap()
+                .iter()
+                .filter(|(_, event)| *event.event_type() == EventType::NewCommitToSelf)
+                .count()
+        };
+        let push_without_commits = push.replace(
+            &push[push.find(r#""commits""#).unwrap()..push.find(r#""repository""#).unwrap()],
+            r#""commits": [], "#,
+        );
+        let (_, event) =
+            &events_from_delivery("push", push_without_commits.as_bytes(), &served_repos).unwrap()
+                [0];
+        assert_eq!(event.client_payload().details().changed_paths(), None);
+
+        assert_eq!(self_events("refs/heads/release/v0.46"), 1);
+        assert_eq!(self_events("refs/heads/feature"), 0);
+
+        // Pushes to branches of a dependency that are not tracked are not propagated.
+        let push = push.replace(
+            "refs/heads/master",
+            "refs/heads/dependabot/cargo/serde-1.0.190",
+        );
+        assert!(events_from_delivery("push", push.as_bytes(), &served_repos)
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn convert_pull_request_to_events() {
+        let served_repos = vec![
+            served_repo("fuels-rs", &["fuel-core"]),
+            served_repo("fuel-core", &[]),
+        ];
+        let pull_request = |action: &str| {
+            format!(
+                r#"{{
+                    "action": "{action}",
+                    "number": 1432,
+                    "pull_request": {{
+                        "number": 1432,
+                        "title": "Add new opcode",
+                        "head": {{ "sha": "9e3b1c7a" }}
+                    }},
+                    "repository": {{ "full_name": "FuelLabs/fuel-core", "default_branch": "master" }}
+                }}"#
+            )
+        };
+
+        let events = events_from_delivery(
+            "pull_request",
+            pull_request("synchronize").as_bytes(),
+            &served_repos,
+        )
+        .unwrap();
+        assert_eq!(events.len(), 1);
+        let (served_repo, event) = &events[0];
+        assert_eq!(served_repo.repo.name(), "fuels-rs");
+        assert_eq!(event.event_type(), &EventType::NewPullRequest);
+        let details = event.client_payload().details();
+        assert_eq!(details.commit_hash().unwrap(), "9e3b1c7a");
+        assert_eq!(details.pull_request().unwrap().number(), 1432);
+        assert!(!details.pull_request().unwrap().is_closed());
+
+        let events = events_from_delivery(
+            "pull_request",
+            pull_request("closed").as_bytes(),
+            &served_repos,
+        )
+        .unwrap();
+        let details = events[0].1.client_payload().details();
+        assert!(details.pull_request().unwrap().is_closed());
+
+        let events = events_from_delivery(
+            "pull_request",
+            pull_request("labeled").as_bytes(),
+            &served_repos,
+        )
+        .unwrap();
+        assert!(events.is_empty());
+    }
+
+    #[test]
+    fn convert_repository_dispatch_to_event() {
+        let served_repos = vec![served_repo("sway", &["fuel-core"])];
+        let dispatch = r#"{
+            "action": "docs-published",
+            "client_payload": {
+                "repo": { "name": "fuel-core", "owner": "FuelLabs" },
+                "details": { "commit_hash": "337d0eaa", "release_tag": null }
+            },
+            "repository": { "full_name": "FuelLabs/sway" }
+        }"#;
+
+        let events =
+            events_from_delivery("repository_dispatch", dispatch.as_bytes(), &served_repos)
+                .unwrap();
+        assert_eq!(events.len(), 1);
+        assert_eq!(
+            events[0].1.event_type(),
+            &EventType::Custom("docs-published".to_string())
+        );
+        assert_eq!(events[0].1.client_payload().repo().name(), "fuel-core");
+
+        // Events of newer emitters are queued, and skipped by the handler if they are unknown.
+        let dispatch = dispatch.replace("docs-published", "Audit_Requested");
+        let events =
+            events_from_delivery("repository_dispatch", dispatch.as_bytes(), &served_repos)
+                .unwrap();
+        assert_eq!(
+            events[0].1.event_type(),
+            &EventType::Custom("Audit_Requested".to_string())
+        );
+        assert!(events_from_delivery("ping", b"{}", &served_repos)
+            .unwrap()
+            .is_empty());
+    }
+}
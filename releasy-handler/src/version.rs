@@ -0,0 +1,153 @@
+//! Version bumps of the packages of a checkout, opened as pull requests by release trains.
+use std::{path::Path, process::Command};
+
+use releasy_graph::manifest::Ecosystem;
+use toml_edit::{Item, TableLike};
+
+use crate::handle::ReleasyHandlerCommand;
+
+/// Bumps the packages of the checkout released together with the repo to `version`, and returns
+/// the version they are bumped from.
+///
+/// The released version is read from `workspace.package.version` of the root `Cargo.toml`, or from
+/// `package.version` if it is not a workspace. Packages and path dependencies of every tracked
+/// `Cargo.toml` are only bumped if they are at the released version, so that packages versioned
+/// independently are left alone.
+pub(crate) fn bump_version(
+    version: &str,
+    ecosystem: &Ecosystem,
+    repo_path: &Path,
+) -> anyhow::Result<String> {
+    if *ecosystem != Ecosystem::Cargo {
+        anyhow::bail!("bumping {ecosystem:?} versions is not supported, declare a bump command");
+    }
+    let root_manifest = std::fs::read_to_string(repo_path.join("Cargo.toml"))?;
+    let previous_version = released_version(&root_manifest)?;
+
+    let output = Command::new("git")
+        .arg("ls-files")
+        .arg(":(glob)**/Cargo.toml")
+        .current_dir(repo_path)
+        .output()?;
+    if !output.status.success() {
+        anyhow::bail!("cannot list the Cargo.toml files of the checkout");
+    }
+    for manifest_path in String::from_utf8_lossy(&output.stdout).lines() {
+        let manifest_path = repo_path.join(manifest_path);
+        let contents = std::fs::read_to_string(&manifest_path)?;
+        let bumped = bump_manifest(&contents, &previous_version, version)?;
+        if bumped != contents {
+            std::fs::write(&manifest_path, bumped)?;
+        }
+    }
+
+    // Only the workspace packages are re-resolved, so that other locked versions are kept.
+    if repo_path.join("Cargo.lock").exists() {
+        ReleasyHandlerCommand::new("cargo")
+            .arg("update")
+            .arg("--workspace")
+            .current_dir(repo_path)
+            .execute()?;
+    }
+    Ok(previous_version)
+}
+
+/// Returns the version released with the repo, declared in its root `Cargo.toml`.
+fn released_version(contents: &str) -> anyhow::Result<String> {
+    let document = contents.parse::<toml_edit::Document>()?;
+    let package_version = |table: Option<&Item>| {
+        table
+            .and_then(|table| table.get("package"))
+            .and_then(|package| package.get("version"))
+            .and_then(Item::as_str)
+            .map(str::to_string)
+    };
+    package_version(document.get("workspace"))
+        .or_else(|| package_version(Some(document.as_item())))
+        .ok_or_else(|| anyhow::anyhow!("the root Cargo.toml does not declare a version"))
+}
+
+/// Sets the version of the package and of its path dependencies to `version`, if they are at
+/// `previous_version`.
+fn bump_manifest(contents: &str, previous_version: &str, version: &str) -> anyhow::Result<String> {
+    let mut document = contents.parse::<toml_edit::Document>()?;
+    let bump_package = |table: Option<&mut Item>| {
+        let Some(package) = table.and_then(|table| table.get_mut("package")) else {
+            return;
+        };
+        if package.get("version").and_then(Item::as_str) == Some(previous_version) {
+            package["version"] = toml_edit::value(version);
+        }
+    };
+    bump_package(Some(document.as_item_mut()));
+    bump_package(document.get_mut("workspace"));
+    bump_path_dependencies(document.as_table_mut(), previous_version, version);
+    Ok(document.to_string())
+}
+
+fn bump_path_dependencies(table: &mut dyn TableLike, previous_version: &str, version: &str) {
+    for (_, item) in table.iter_mut() {
+        let Some(entry) = item.as_table_like_mut() else {
+            continue;
+        };
+        if !entry.contains_key("path") {
+            bump_path_dependencies(entry, previous_version, version);
+            continue;
+        }
+        // Keep the operator of the requirement, e.g. `=0.20.0`.
+        let requirement = entry.get("version").and_then(Item::as_str).and_then(|req| {
+            let operator = req.trim_end_matches(|c: char| c.is_alphanumeric() || ".-+".contains(c));
+            (req[operator.len()..] == *previous_version).then(|| format!("{operator}{version}"))
+        });
+        if let Some(requirement) = requirement {
+            entry.insert("version", toml_edit::value(requirement));
+            if let Some(inline_table) = item.as_inline_table_mut() {
+                inline_table.fmt();
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{bump_manifest, released_version};
+
+    #[test]
+    fn bump_workspace_versions() {
+        let contents = r#"[workspace]
+members = ["crates/*"]
+
+[workspace.package]
+version = "0.20.0"
+
+[workspace.dependencies]
+fuel-core-client = { version = "0.20.0", path = "crates/client" }
+fuel-core-types = { version = "=0.20.0", path = "crates/types", default-features = false }
+fuel-vm = { version = "0.20.0" }
+fuel-core-metrics = { version = "0.1.2", path = "crates/metrics" }
+"#;
+        assert_eq!(released_version(contents).unwrap(), "0.20.0");
+        assert_eq!(
+            bump_manifest(contents, "0.20.0", "0.21.0").unwrap(),
+            r#"[workspace]
+members = ["crates/*"]
+
+[workspace.package]
+version = "0.21.0"
+
+[workspace.dependencies]
+fuel-core-client = { version = "0.21.0", path = "crates/client" }
+fuel-core-types = { version = "=0.21.0", path = "crates/types", default-features = false }
+fuel-vm = { version = "0.20.0" }
+fuel-core-metrics = { version = "0.1.2", path = "crates/metrics" }
+"#
+        );
+
+        let package = "[package]\nname = \"forc-wallet\"\nversion = \"0.3.0\"\n";
+        assert_eq!(released_version(package).unwrap(), "0.3.0");
+        assert_eq!(
+            bump_manifest(package, "0.3.0", "0.4.0").unwrap(),
+            "[package]\nname = \"forc-wallet\"\nversion = \"0.4.0\"\n"
+        );
+    }
+}
@@ -0,0 +1,56 @@
+use std::path::PathBuf;
+
+use clap::{Args, ValueEnum};
+use releasy_handler::changelog::changelog;
+
+use super::load_manifest;
+
+/// Flags of the `changelog` command.
+#[derive(Args, Debug)]
+pub(crate) struct ChangelogArgs {
+    /// Repo to collect the changelog of, as `<owner>/<name>` or its name.
+    ///
+    /// By default the current repo of the manifest.
+    #[arg(long)]
+    pub(crate) repo: Option<String>,
+
+    /// Tag, or any other revision, the changelog starts after.
+    #[arg(long)]
+    pub(crate) from: String,
+
+    /// Tag, or any other revision, the changelog ends at.
+    #[arg(long)]
+    pub(crate) to: String,
+
+    /// Directory containing the local checkouts, each named after its repo.
+    #[arg(long)]
+    pub(crate) checkouts: PathBuf,
+
+    /// Format of the changelog.
+    #[arg(long, value_enum, default_value_t = ChangelogFormat::Markdown)]
+    pub(crate) output: ChangelogFormat,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum ChangelogFormat {
+    Markdown,
+    Json,
+}
+
+pub(crate) fn exec(path: Option<PathBuf>, args: ChangelogArgs) -> anyhow::Result<()> {
+    let manifest = load_manifest(path)?;
+    let repo = args.repo.unwrap_or_else(|| {
+        let current_repo = manifest.current_repo();
+        format!("{}/{}", current_repo.owner(), current_repo.name())
+    });
+    let changelog = changelog(&manifest, &repo, &args.from, &args.to, &args.checkouts)?;
+    // Warnings go to stderr, so that the changelog can be redirected to a file as is.
+    for warning in changelog.warnings() {
+        eprintln!("WARNING: {warning}");
+    }
+    match args.output {
+        ChangelogFormat::Markdown => println!("{}", changelog.to_markdown()),
+        ChangelogFormat::Json => println!("{}", serde_json::to_string_pretty(&changelog)?),
+    }
+    Ok(())
+}
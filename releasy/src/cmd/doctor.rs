@@ -0,0 +1,111 @@
+use std::{
+    fmt::Display,
+    path::{Path, PathBuf},
+};
+
+use releasy_core::repo::Repo;
+use releasy_emit::context::repo_from_url;
+use releasy_graph::{manifest::ManifestFile, plan::Plan};
+use releasy_handler::handle::git_output;
+
+use super::{manifest_path, same_repo};
+
+/// Outcome of a single environment check.
+enum CheckStatus {
+    Ok(String),
+    /// Something that only affects some of the commands.
+    Warn(String),
+    Fail(String),
+}
+
+impl Display for CheckStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CheckStatus::Ok(message) => write!(f, "[ok]   {message}"),
+            CheckStatus::Warn(message) => write!(f, "[warn] {message}"),
+            CheckStatus::Fail(message) => write!(f, "[fail] {message}"),
+        }
+    }
+}
+
+/// Checks that git is available, the manifest is valid, tokens required by the commands are set
+/// and the manifest's current repo matches the local checkout.
+pub(crate) fn exec(path: Option<PathBuf>) -> anyhow::Result<()> {
+    let mut checks = vec![check_git()];
+    let current_repo = match check_manifest(path) {
+        Ok((status, current_repo)) => {
+            checks.push(status);
+            Some(current_repo)
+        }
+        Err(e) => {
+            checks.push(CheckStatus::Fail(format!("manifest: {e}")));
+            None
+        }
+    };
+    checks.push(check_env_var("DISPATCH_TOKEN", "emit"));
+    checks.push(check_env_var("GITHUB_TOKEN", "handle"));
+    checks.push(check_env_var("GITHUB_ACTOR", "handle"));
+    if let Some(current_repo) = current_repo {
+        checks.push(check_origin(&current_repo));
+    }
+
+    for check in &checks {
+        println!("{check}");
+    }
+    let failures = checks
+        .iter()
+        .filter(|check| matches!(check, CheckStatus::Fail(_)))
+        .count();
+    if failures > 0 {
+        anyhow::bail!("{failures} checks failed");
+    }
+    Ok(())
+}
+
+fn check_git() -> CheckStatus {
+    match git_output(&["--version"], Path::new(".")) {
+        Ok(version) => CheckStatus::Ok(version),
+        Err(_) => CheckStatus::Fail("git is not available".to_string()),
+    }
+}
+
+fn check_manifest(path: Option<PathBuf>) -> anyhow::Result<(CheckStatus, Repo)> {
+    let manifest_file = ManifestFile::from_file(&manifest_path(path)?)?;
+    let warnings = manifest_file.warnings().count();
+    let manifest = manifest_file.manifest();
+    let current_repo = manifest.current_repo().clone();
+    let plan = Plan::try_from_manifest(manifest)?;
+    let upstream_repos = plan.upstream_repos(current_repo.clone())?.count();
+    let status = if warnings > 0 {
+        CheckStatus::Warn(format!(
+            "manifest is valid with {warnings} warnings, run `releasy validate` to see them"
+        ))
+    } else {
+        CheckStatus::Ok(format!(
+            "manifest is valid, current repo has {upstream_repos} upstream repos"
+        ))
+    };
+    Ok((status, current_repo))
+}
+
+fn check_env_var(name: &str, command: &str) -> CheckStatus {
+    match std::env::var(name) {
+        Ok(_) => CheckStatus::Ok(format!("{name} is set")),
+        Err(_) => CheckStatus::Warn(format!("{name} is not set, `{command}` needs it")),
+    }
+}
+
+/// Checks that the origin remote of the local checkout points to the current repo.
+fn check_origin(current_repo: &Repo) -> CheckStatus {
+    let Ok(origin) = git_output(&["remote", "get-url", "origin"], Path::new(".")) else {
+        return CheckStatus::Warn("not in a git checkout with an origin remote".to_string());
+    };
+    match repo_from_url(&origin) {
+        Some(origin_repo) if same_repo(&origin_repo, current_repo) => {
+            CheckStatus::Ok(format!("origin ({origin}) matches the current repo"))
+        }
+        _ => CheckStatus::Warn(format!(
+            "origin ({origin}) does not match the current repo ({current_repo})"
+        )),
+    }
+}
@@ -0,0 +1,237 @@
+use std::{collections::HashMap, path::PathBuf, process::ExitCode};
+
+use clap::{Args, ValueEnum};
+use releasy_core::{
+    delivery::Delivery,
+    event::{ClientPayload, Event},
+};
+use releasy_emit::{
+    context::{with_commit_metadata, EventContext},
+    emit,
+    outbox::{emit_with_outbox, OutboxLocation, DEFAULT_OUTBOX_FILE_NAME},
+    report::EmitReport,
+    target::{resolve_targets, RepoSelector, Target, TargetSelection},
+    DEFAULT_CONCURRENCY,
+};
+use releasy_graph::{manifest::Manifest, plan::Plan};
+
+use super::{load_manifest, same_repo, EventArgs};
+
+/// Flags of the `emit` command. The repo emitting the event is the current repo of the manifest.
+#[derive(Args, Debug)]
+pub(crate) struct EmitArgs {
+    #[command(flatten)]
+    pub(crate) event: EventArgs,
+
+    /// Maximum number of target repos the event is sent to concurrently.
+    #[arg(long, default_value_t = DEFAULT_CONCURRENCY)]
+    pub(crate) concurrency: usize,
+
+    /// Format of the report listing the outcome for each target repo.
+    #[arg(long, value_enum, default_value_t = OutputFormat::Table)]
+    pub(crate) output: OutputFormat,
+
+    /// Print the event and the target repos without sending the event.
+    #[arg(long)]
+    pub(crate) dry_run: bool,
+
+    /// Only target the matching repos. Accepts `<name>`, `<owner>/<name>`, `<owner>/*` or
+    /// `group:<group>`, and can be repeated.
+    #[arg(long)]
+    pub(crate) only: Vec<RepoSelector>,
+
+    /// Do not target the matching repos. Accepts the same forms as `--only`, and can be repeated.
+    #[arg(long)]
+    pub(crate) exclude: Vec<RepoSelector>,
+
+    /// Target every repo depending on the current repo, directly or transitively.
+    #[arg(long)]
+    pub(crate) transitive: bool,
+
+    /// Where events are kept until they are delivered, so that `releasy replay` can redeliver
+    /// them. Either a file path or `git:<ref>` to keep them in a ref pushed to `origin`.
+    ///
+    /// The file outbox is the default so that emitting does not push to the repo, but it does not
+    /// outlive ephemeral CI runners: use `git:refs/releasy/outbox` in CI. A warning is printed
+    /// when undelivered events are left in a file outbox while `CI=true`.
+    #[arg(long, default_value = DEFAULT_OUTBOX_FILE_NAME)]
+    pub(crate) outbox: OutboxLocation,
+
+    /// Do not keep undelivered events in the outbox.
+    #[arg(long, conflicts_with = "outbox")]
+    pub(crate) no_outbox: bool,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum OutputFormat {
+    Table,
+    Json,
+}
+
+/// Emits the event and prints the report.
+///
+/// Exits with 1 if no target could be notified and with 2 if only some of them could be notified.
+pub(crate) async fn exec(path: Option<PathBuf>, args: EmitArgs) -> anyhow::Result<ExitCode> {
+    let manifest = load_manifest(path)?;
+    let current_repo = manifest.current_repo().clone();
+    let context = EventContext::detect();
+    if let Some(detected_repo) = context.repo() {
+        if !same_repo(detected_repo, &current_repo) {
+            println!(
+                "WARNING: current repo of the manifest ({current_repo}) does not match the \
+                 checkout ({detected_repo})"
+            );
+        }
+    }
+    let event_args = args.event.or_detected(&context);
+    let event_type = event_args.event_type()?;
+    let details = with_commit_metadata(event_args.into_details());
+    let event = Event::new(
+        event_type,
+        ClientPayload::new(current_repo.clone(), details),
+    );
+    let selection =
+        TargetSelection::new(args.only, args.exclude, args.transitive).with_groups(&manifest);
+    let targets = resolve_delivered_targets(&event, manifest, &selection)?;
+
+    if args.dry_run {
+        match args.output {
+            OutputFormat::Table => {
+                println!("{}", serde_json::to_string_pretty(&event)?);
+                for target in &targets {
+                    match target.skip_reason() {
+                        Some(reason) => println!("skip {} ({reason})", target.repo()),
+                        None => match target.delivery() {
+                            Delivery::RepositoryDispatch => println!("send {}", target.repo()),
+                            Delivery::WorkflowDispatch { workflow, git_ref } => println!(
+                                "send {} (workflow {workflow} on {})",
+                                target.repo(),
+                                git_ref.as_deref().unwrap_or("its default branch")
+                            ),
+                        },
+                    }
+                }
+            }
+            OutputFormat::Json => {
+                let dry_run = serde_json::json!({ "event": event, "targets": targets });
+                println!("{}", serde_json::to_string_pretty(&dry_run)?);
+            }
+        }
+        return Ok(ExitCode::SUCCESS);
+    }
+
+    let report = if args.no_outbox {
+        emit(&event, targets, args.concurrency).await?
+    } else {
+        let report = emit_with_outbox(&event, targets, args.concurrency, &args.outbox).await?;
+        warn_if_outbox_left_on_ci(&args.outbox);
+        report
+    };
+    print_report(&report, args.output)
+}
+
+/// Warns that undelivered events are lost with the checkout if they are left in a file outbox on a
+/// CI runner.
+pub(crate) fn warn_if_outbox_left_on_ci(outbox: &OutboxLocation) {
+    let on_ci = std::env::var("CI").is_ok_and(|ci| ci == "true");
+    if let OutboxLocation::File(path) = outbox {
+        // The file is removed once the outbox is empty.
+        if on_ci && path.exists() {
+            println!(
+                "WARNING: undelivered events are kept in {}, which is lost with the CI runner. \
+                 Use `--outbox git:refs/releasy/outbox` to keep them in the repo.",
+                path.display()
+            );
+        }
+    }
+}
+
+/// Resolves the targets of an event emitted by the current repo of the manifest, each with the
+/// delivery declared in its settings.
+pub(crate) fn resolve_delivered_targets(
+    event: &Event,
+    manifest: Manifest,
+    selection: &TargetSelection,
+) -> anyhow::Result<Vec<Target>> {
+    let current_repo = manifest.current_repo().clone();
+    let deliveries = manifest
+        .repos()
+        .map(|repo| (repo.clone(), manifest.settings(repo).delivery()))
+        .collect::<HashMap<_, _>>();
+    let plan = Plan::try_from_manifest(manifest)?;
+    let targets = resolve_targets(event, &plan, &current_repo, selection)?
+        .into_iter()
+        .map(|target| {
+            let delivery = deliveries.get(target.repo()).cloned().unwrap_or_default();
+            target.with_delivery(delivery)
+        })
+        .collect();
+    Ok(targets)
+}
+
+/// Prints the report and returns the exit code summarizing it.
+pub(crate) fn print_report(report: &EmitReport, output: OutputFormat) -> anyhow::Result<ExitCode> {
+    match output {
+        OutputFormat::Table => println!("{report}"),
+        OutputFormat::Json => println!("{}", serde_json::to_string_pretty(report)?),
+    }
+    Ok(ExitCode::from(report.exit_code()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{EmitArgs, OutputFormat};
+    use crate::cmd::{load_manifest, EventArgs};
+    use releasy_core::{
+        event::{ClientPayload, Event, EventDetails, EventType},
+        repo::Repo,
+    };
+    use releasy_emit::outbox::OutboxLocation;
+    use releasy_emit::DEFAULT_CONCURRENCY;
+    use std::path::PathBuf;
+
+    const SWAY_WALLET_SDK_TEST_MANIFEST_FILE_NAME: &str = "repo-plan-sway-wallet-sdk.toml";
+
+    #[test]
+    fn parse_event_from_param_input() {
+        let test_manifest_file = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+            .join("tests")
+            .join(SWAY_WALLET_SDK_TEST_MANIFEST_FILE_NAME);
+        let repo_name = "fuels-rs".to_string();
+        let repo_owner = "FuelLabs".to_string();
+        let event_type = "new-commit-to-dependency".to_string();
+
+        let expected_commit_hash = "337d0eaa130dd18e9e347f83ab4fab76b3a6bd2a".to_string();
+        let args = EmitArgs {
+            event: EventArgs {
+                event: Some(event_type),
+                event_commit_hash: Some(expected_commit_hash.clone()),
+                event_release_tag: None,
+                event_branch: None,
+                event_base_commit_hash: None,
+                event_pull_request: None,
+                event_pull_request_closed: false,
+            },
+            concurrency: DEFAULT_CONCURRENCY,
+            output: OutputFormat::Table,
+            dry_run: false,
+            only: vec![],
+            exclude: vec![],
+            transitive: false,
+            outbox: OutboxLocation::default(),
+            no_outbox: false,
+        };
+
+        let manifest = load_manifest(Some(test_manifest_file)).unwrap();
+        let parsed_event = args
+            .event
+            .into_event(manifest.current_repo().clone())
+            .unwrap();
+        let sway_repo = Repo::new(repo_name, repo_owner);
+        let details = EventDetails::new(Some(expected_commit_hash), None);
+        let client_payload = ClientPayload::new(sway_repo, details);
+        let expected_event = Event::new(EventType::NewCommitToDependency, client_payload);
+
+        assert_eq!(parsed_event, expected_event)
+    }
+}
@@ -0,0 +1,115 @@
+use std::path::{Path, PathBuf};
+
+use clap::{Args, Subcommand};
+use releasy_core::default::DEFAULT_MANIFEST_FILE_NAME;
+use releasy_graph::{consistency::check_consistency, infer::infer_dependencies};
+
+use super::load_manifest;
+
+/// Locations of the plan copy inside a checkout, in the order they are looked up.
+const PLAN_COPY_LOCATIONS: [&str; 2] = [".github/workflows", "."];
+
+/// Flags of the `graph` command.
+#[derive(Args, Debug)]
+pub(crate) struct GraphArgs {
+    #[command(subcommand)]
+    pub(crate) command: GraphCommand,
+}
+
+#[derive(Subcommand, Debug)]
+pub(crate) enum GraphCommand {
+    /// Infer dependencies of the repos from their local checkouts and diff them against the
+    /// dependencies declared in the manifest.
+    Infer {
+        /// Directory containing the local checkouts, each named after its repo.
+        #[arg(long)]
+        checkouts: PathBuf,
+
+        /// Fail if declared dependencies are out of sync with the inferred ones.
+        #[arg(long)]
+        check: bool,
+    },
+    /// Check that copies of the plan carried by different repos agree on the graph.
+    ///
+    /// Fails if any of the copies disagree. The manifest given with `--path` is not compared
+    /// unless it is listed in `paths` as well.
+    Check {
+        /// Paths of the plan copies to compare.
+        paths: Vec<PathBuf>,
+
+        /// Directory containing local checkouts, whose plan copies are compared as well.
+        ///
+        /// Copies are looked up at `.github/workflows/repo-plan.toml` and `repo-plan.toml` of
+        /// each checkout.
+        #[arg(long)]
+        checkouts: Option<PathBuf>,
+    },
+}
+
+pub(crate) fn exec(path: Option<PathBuf>, args: GraphArgs) -> anyhow::Result<()> {
+    match args.command {
+        GraphCommand::Infer { checkouts, check } => {
+            let manifest = load_manifest(path)?;
+            let diffs = infer_dependencies(&manifest, &checkouts)?;
+            for diff in &diffs {
+                println!("{diff}");
+            }
+            let out_of_sync = diffs
+                .iter()
+                .filter(|diff| !diff.is_in_sync())
+                .map(|diff| diff.key())
+                .collect::<Vec<_>>();
+            if check && !out_of_sync.is_empty() {
+                anyhow::bail!(
+                    "dependencies of [{}] are out of sync with the local checkouts",
+                    out_of_sync.join(", ")
+                );
+            }
+        }
+        GraphCommand::Check { paths, checkouts } => {
+            let mut copies = vec![];
+            for path in paths {
+                copies.push((path.display().to_string(), load_manifest(Some(path))?));
+            }
+            if let Some(checkouts) = checkouts {
+                for (label, path) in plan_copies_in_checkouts(&checkouts)? {
+                    copies.push((label, load_manifest(Some(path))?));
+                }
+            }
+            let inconsistencies = check_consistency(&copies)?;
+            for inconsistency in &inconsistencies {
+                println!("{inconsistency}");
+            }
+            if !inconsistencies.is_empty() {
+                anyhow::bail!(
+                    "{} inconsistencies found between {} plan copies",
+                    inconsistencies.len(),
+                    copies.len()
+                );
+            }
+            println!("{} plan copies are consistent", copies.len());
+        }
+    }
+    Ok(())
+}
+
+/// Returns the plan copies found in the checkouts under `checkouts`, labeled by checkout name.
+fn plan_copies_in_checkouts(checkouts: &Path) -> anyhow::Result<Vec<(String, PathBuf)>> {
+    let mut copies = vec![];
+    for entry in std::fs::read_dir(checkouts)? {
+        let checkout = entry?.path();
+        let copy = PLAN_COPY_LOCATIONS
+            .iter()
+            .map(|location| checkout.join(location).join(DEFAULT_MANIFEST_FILE_NAME))
+            .find(|path| path.is_file());
+        if let Some(copy) = copy {
+            let label = checkout
+                .file_name()
+                .map(|name| name.to_string_lossy().to_string())
+                .unwrap_or_default();
+            copies.push((label, copy));
+        }
+    }
+    copies.sort();
+    Ok(copies)
+}
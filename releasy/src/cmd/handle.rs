@@ -0,0 +1,153 @@
+use std::path::PathBuf;
+
+use clap::Args;
+use releasy_core::{
+    delivery::WorkflowInputs,
+    event::{ClientPayload, Event, EventDetails},
+    repo::Repo,
+};
+use releasy_handler::handle::EventHandler;
+
+use super::{load_manifest, EventArgs};
+
+/// Flags of the `handle` command.
+#[derive(Args, Debug)]
+pub(crate) struct HandleArgs {
+    #[command(flatten)]
+    pub(crate) event: EventArgs,
+
+    /// Name of the repo emitted this event.
+    #[arg(long)]
+    pub(crate) event_repo_name: Option<String>,
+
+    /// Owner of the repo emitted this event.
+    #[arg(long)]
+    pub(crate) event_repo_owner: Option<String>,
+
+    /// Details of the event as JSON, i.e. `client_payload.details` of the dispatched event.
+    ///
+    /// Carries the commit metadata sent by the emitter, and overrides the other `--event-*`
+    /// details if provided.
+    #[arg(long)]
+    pub(crate) event_details: Option<String>,
+
+    /// Client payload of the event as JSON, i.e. the `client_payload` input of workflows
+    /// triggered with `workflow-dispatch` delivery.
+    ///
+    /// Overrides the repo and details of the event provided with the other flags.
+    #[arg(long)]
+    pub(crate) event_client_payload: Option<String>,
+}
+
+impl TryFrom<HandleArgs> for Event {
+    type Error = anyhow::Error;
+
+    fn try_from(value: HandleArgs) -> Result<Self, Self::Error> {
+        if let Some(client_payload) = value.event_client_payload {
+            let event_type = value.event.event_type()?.to_string();
+            let inputs = WorkflowInputs {
+                event_type,
+                client_payload,
+            };
+            return Ok(Event::try_from(inputs)?);
+        }
+        let event_repo_name = value
+            .event_repo_name
+            .ok_or_else(|| anyhow::anyhow!("repo name should not be emtpy"))?;
+        let event_repo_owner = value
+            .event_repo_owner
+            .ok_or_else(|| anyhow::anyhow!("repo owner should not be emtpy"))?;
+        let repo = Repo::new(event_repo_name, event_repo_owner);
+        match value.event_details {
+            Some(details) => {
+                let details = serde_json::from_str::<EventDetails>(&details)?;
+                let event_type = value.event.event_type()?;
+                Ok(Event::new(event_type, ClientPayload::new(repo, details)))
+            }
+            None => value.event.into_event(repo),
+        }
+    }
+}
+
+pub(crate) fn exec(path: Option<PathBuf>, args: HandleArgs) -> anyhow::Result<()> {
+    let received_event = Event::try_from(args)?;
+    let manifest = load_manifest(path)?;
+    received_event.handle(manifest)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::HandleArgs;
+    use crate::cmd::EventArgs;
+    use releasy_core::{
+        event::{ClientPayload, Event, EventDetails, EventType},
+        repo::Repo,
+    };
+
+    #[test]
+    fn parse_event_from_param_input() {
+        let repo_name = "fuels-rs".to_string();
+        let repo_owner = "FuelLabs".to_string();
+        let event_type = "new-commit-to-dependency".to_string();
+
+        let expected_commit_hash = "337d0eaa130dd18e9e347f83ab4fab76b3a6bd2a".to_string();
+        let args = HandleArgs {
+            event: EventArgs {
+                event: Some(event_type),
+                event_commit_hash: Some(expected_commit_hash.clone()),
+                event_release_tag: None,
+                event_branch: None,
+                event_base_commit_hash: None,
+                event_pull_request: None,
+                event_pull_request_closed: false,
+            },
+            event_repo_name: Some(repo_name.clone()),
+            event_repo_owner: Some(repo_owner.clone()),
+            event_details: None,
+            event_client_payload: None,
+        };
+
+        let parsed_event = Event::try_from(args).unwrap();
+        let fuels_rs_repo = Repo::new(repo_name, repo_owner);
+        let details = EventDetails::new(Some(expected_commit_hash), None);
+        let client_payload = ClientPayload::new(fuels_rs_repo, details);
+        let expected_event = Event::new(EventType::NewCommitToDependency, client_payload);
+
+        assert_eq!(parsed_event, expected_event)
+    }
+
+    #[test]
+    fn parse_event_details_from_json() {
+        let details = r#"{
+            "commit_hash": "337d0eaa130dd18e9e347f83ab4fab76b3a6bd2a",
+            "release_tag": null,
+            "subject": "Bump fuel-core [skip releasy]",
+            "changed_paths": ["Cargo.toml"]
+        }"#;
+        let args = HandleArgs {
+            event: EventArgs {
+                event: Some("new-commit-to-dependency".to_string()),
+                event_commit_hash: None,
+                event_release_tag: None,
+                event_branch: None,
+                event_base_commit_hash: None,
+                event_pull_request: None,
+                event_pull_request_closed: false,
+            },
+            event_repo_name: Some("fuels-rs".to_string()),
+            event_repo_owner: Some("FuelLabs".to_string()),
+            event_details: Some(details.to_string()),
+            event_client_payload: None,
+        };
+
+        let parsed_event = Event::try_from(args).unwrap();
+        let details = parsed_event.client_payload().details();
+        assert_eq!(
+            details.commit_hash().unwrap(),
+            "337d0eaa130dd18e9e347f83ab4fab76b3a6bd2a"
+        );
+        assert_eq!(details.changed_paths().unwrap(), ["Cargo.toml"]);
+        assert!(details.is_skipped());
+    }
+}
@@ -0,0 +1,196 @@
+pub(crate) mod changelog;
+pub(crate) mod doctor;
+pub(crate) mod emit;
+pub(crate) mod graph;
+pub(crate) mod handle;
+pub(crate) mod reconcile;
+pub(crate) mod release_train;
+pub(crate) mod replay;
+pub(crate) mod report_status;
+pub(crate) mod serve;
+pub(crate) mod status;
+pub(crate) mod validate;
+
+use std::{env::current_dir, path::PathBuf, str::FromStr};
+
+use clap::{Parser, Subcommand};
+use releasy_core::{
+    default::DEFAULT_MANIFEST_FILE_NAME,
+    event::{ClientPayload, Event, EventDetails, EventType, PullRequest},
+    repo::Repo,
+};
+use releasy_emit::context::EventContext;
+use releasy_graph::manifest::{Manifest, ManifestFile};
+
+/// Command line tool to emit and handle releasy events across the repos described by a repo plan.
+///
+///
+/// Event details can be provided via flags:
+///
+/// ```text
+/// releasy emit --event "new-commit-to-dependency" --event-commit-hash "<commit-hash>"
+/// releasy handle --event "new-commit-to-dependency" --event-repo-name "repo-name" --event-repo-owner "repo-owner"
+/// ```
+#[derive(Parser, Debug)]
+#[command(author, version, about, long_about = None)]
+pub(crate) struct Cli {
+    /// Path to the manifest file describing repo plan.
+    ///
+    /// By default `repo-plan.toml` expected to be in the current dir.
+    #[arg(long, global = true)]
+    pub(crate) path: Option<PathBuf>,
+
+    #[command(subcommand)]
+    pub(crate) command: Command,
+}
+
+#[derive(Subcommand, Debug)]
+pub(crate) enum Command {
+    /// Emit an event to the downstream repos of the current repo.
+    Emit(emit::EmitArgs),
+    /// Redeliver events that could not be delivered by `emit`.
+    Replay(replay::ReplayArgs),
+    /// Handle an event received by the current repo.
+    Handle(handle::HandleArgs),
+    /// Handle events delivered by GitHub webhooks until stopped.
+    Serve(serve::ServeArgs),
+    /// Inspect the dependency graph described by the repo plan.
+    Graph(graph::GraphArgs),
+    /// Validate the repo plan.
+    Validate(validate::ValidateArgs),
+    /// Converge tracking branches of the current repo to the state described by the repo plan.
+    Reconcile(reconcile::ReconcileArgs),
+    /// Report how far the tracking branches of the current repo drifted from their upstreams.
+    Status(status::StatusArgs),
+    /// Report the CI result of a tracking branch as a commit status on the upstream commit.
+    ReportStatus(report_status::ReportStatusArgs),
+    /// Release repos of the plan in dependency order, opening version bump pull requests and
+    /// emitting `new-release` events once they are tagged.
+    ReleaseTrain(release_train::ReleaseTrainArgs),
+    /// Collect the changelog of a repo between two tags, together with the changes of the upstream
+    /// repos bumped in between, from local checkouts.
+    Changelog(changelog::ChangelogArgs),
+    /// Check that the environment is set up for running releasy.
+    Doctor,
+}
+
+/// Event details shared by the commands creating events from flags.
+#[derive(clap::Args, Debug)]
+pub(crate) struct EventArgs {
+    /// Type of the event.
+    ///
+    /// Possible values: [new-commit-to-dependency, new-release, new-commit-to-self,
+    /// new-pull-request] or a custom
+    /// event declared in the `[events]` table of the manifest.
+    #[arg(long)]
+    pub(crate) event: Option<String>,
+
+    /// Commit hash that triggered this event.
+    ///
+    /// `emit` falls back to `GITHUB_SHA` or the `HEAD` of the local checkout.
+    #[arg(long)]
+    pub(crate) event_commit_hash: Option<String>,
+
+    /// Release tag that triggered this event.
+    ///
+    /// `emit` falls back to the tag in `GITHUB_REF` or the tag pointing at `HEAD`.
+    #[arg(long)]
+    pub(crate) event_release_tag: Option<String>,
+
+    /// Branch that triggered this event.
+    ///
+    /// `emit` falls back to the branch in `GITHUB_REF` or the checked out branch. Otherwise the
+    /// event is assumed to be coming from the default branch.
+    #[arg(long)]
+    pub(crate) event_branch: Option<String>,
+
+    /// Previous commit processed before the commit that triggered this event, so that the event
+    /// covers the changes since then.
+    ///
+    /// `emit` falls back to the previous head of the branch in the GitHub push event.
+    #[arg(long)]
+    pub(crate) event_base_commit_hash: Option<String>,
+
+    /// Number of the pull request that triggered this event, for `new-pull-request` events.
+    ///
+    /// `emit` falls back to the pull request in the GitHub pull request event.
+    #[arg(long)]
+    pub(crate) event_pull_request: Option<u64>,
+
+    /// Marks the pull request of the event as closed, so that downstream repos delete its branch.
+    #[arg(long, requires = "event_pull_request")]
+    pub(crate) event_pull_request_closed: bool,
+}
+
+impl EventArgs {
+    /// Fills the details that are not provided with the ones detected from the environment.
+    pub(crate) fn or_detected(self, context: &EventContext) -> Self {
+        Self {
+            event: self.event,
+            event_commit_hash: self
+                .event_commit_hash
+                .or_else(|| context.commit_hash().cloned()),
+            event_release_tag: self
+                .event_release_tag
+                .or_else(|| context.release_tag().cloned()),
+            event_branch: self.event_branch.or_else(|| context.branch().cloned()),
+            event_base_commit_hash: self
+                .event_base_commit_hash
+                .or_else(|| context.base_commit_hash().cloned()),
+            event_pull_request: self
+                .event_pull_request
+                .or_else(|| context.pull_request().map(PullRequest::number)),
+            event_pull_request_closed: self.event_pull_request_closed
+                || (self.event_pull_request.is_none()
+                    && context.pull_request().is_some_and(PullRequest::is_closed)),
+        }
+    }
+
+    /// Creates an event emitted by `repo` from the provided flags.
+    pub(crate) fn into_event(self, repo: Repo) -> anyhow::Result<Event> {
+        let event_type = self.event_type()?;
+        let client_payload = ClientPayload::new(repo, self.into_details());
+        Ok(Event::new(event_type, client_payload))
+    }
+
+    pub(crate) fn event_type(&self) -> anyhow::Result<EventType> {
+        let event = self
+            .event
+            .as_ref()
+            .ok_or_else(|| anyhow::anyhow!("event should not be emtpy"))?;
+        Ok(EventType::from_str(event)?)
+    }
+
+    pub(crate) fn into_details(self) -> EventDetails {
+        EventDetails::new(self.event_commit_hash, self.event_release_tag)
+            .with_branch(self.event_branch)
+            .with_base_commit_hash(self.event_base_commit_hash)
+            .with_pull_request(
+                self.event_pull_request.map(|number| {
+                    PullRequest::new(number).with_closed(self.event_pull_request_closed)
+                }),
+            )
+    }
+}
+
+/// Returns true if both repos refer to the same GitHub repo, which are case insensitive.
+pub(crate) fn same_repo(a: &Repo, b: &Repo) -> bool {
+    a.owner().eq_ignore_ascii_case(b.owner()) && a.name().eq_ignore_ascii_case(b.name())
+}
+
+/// Returns the path of the manifest, `repo-plan.toml` in the current dir by default.
+pub(crate) fn manifest_path(path: Option<PathBuf>) -> anyhow::Result<PathBuf> {
+    match path {
+        Some(path) => Ok(path),
+        None => Ok(current_dir()?.join(DEFAULT_MANIFEST_FILE_NAME)),
+    }
+}
+
+/// Loads the manifest at the given path, printing the warnings produced while parsing it.
+pub(crate) fn load_manifest(path: Option<PathBuf>) -> anyhow::Result<Manifest> {
+    let manifest_file = ManifestFile::from_file(&manifest_path(path)?)?;
+    for warning in manifest_file.warnings() {
+        println!("WARNING: {warning}");
+    }
+    Ok(manifest_file.manifest())
+}
@@ -0,0 +1,51 @@
+use std::{path::PathBuf, process::ExitCode};
+
+use clap::Args;
+use releasy_handler::{
+    handle::Workspace,
+    reconcile::{reconcile, ReconcileOptions},
+};
+
+use super::{emit::OutputFormat, load_manifest};
+
+/// Flags of the `reconcile` command.
+#[derive(Args, Debug)]
+pub(crate) struct ReconcileArgs {
+    /// Print what would be done to each tracking branch without pushing anything.
+    #[arg(long)]
+    pub(crate) dry_run: bool,
+
+    /// Delete `upgrade/*` branches that do not track any dependency of the plan, instead of only
+    /// reporting them.
+    #[arg(long)]
+    pub(crate) delete_orphaned: bool,
+
+    /// Format of the report listing the action taken for each tracking branch.
+    #[arg(long, value_enum, default_value_t = OutputFormat::Table)]
+    pub(crate) output: OutputFormat,
+}
+
+/// Reconciles the tracking branches and prints the report.
+///
+/// Exits with 1 if any branch could not be reconciled, the other branches are reconciled
+/// regardless.
+pub(crate) fn exec(path: Option<PathBuf>, args: ReconcileArgs) -> anyhow::Result<ExitCode> {
+    let manifest = load_manifest(path)?;
+    println!(
+        "Reconciling tracking branches of {}",
+        manifest.current_repo()
+    );
+    let options = ReconcileOptions {
+        dry_run: args.dry_run,
+        delete_orphaned: args.delete_orphaned,
+    };
+    let report = reconcile(manifest, &options, &Workspace::Temporary)?;
+    match args.output {
+        OutputFormat::Table => println!("{report}"),
+        OutputFormat::Json => println!("{}", serde_json::to_string_pretty(&report)?),
+    }
+    if report.failures() > 0 {
+        return Ok(ExitCode::FAILURE);
+    }
+    Ok(ExitCode::SUCCESS)
+}
@@ -0,0 +1,61 @@
+use std::{path::PathBuf, process::ExitCode, time::Duration};
+
+use clap::Args;
+use releasy_handler::release_train::{
+    release_train, ReleaseSpec, ReleaseTrainOptions, DEFAULT_STATE_FILE_NAME,
+};
+
+use super::{emit::OutputFormat, load_manifest};
+
+/// Flags of the `release-train` command.
+#[derive(Args, Debug)]
+pub(crate) struct ReleaseTrainArgs {
+    /// Path to the release spec listing the repos to release with their new versions.
+    #[arg(long)]
+    pub(crate) spec: PathBuf,
+
+    /// Path to the file the progress of the train is saved to, and resumed from.
+    #[arg(long, default_value = DEFAULT_STATE_FILE_NAME)]
+    pub(crate) state: PathBuf,
+
+    /// Seconds to wait between checks of pull requests and tags.
+    #[arg(long, default_value_t = 60)]
+    pub(crate) poll_interval: u64,
+
+    /// Stop once the train has to wait for a pull request or a tag, instead of polling until
+    /// every repo is released.
+    #[arg(long)]
+    pub(crate) once: bool,
+
+    /// Print the release order and the saved progress without doing anything.
+    #[arg(long)]
+    pub(crate) dry_run: bool,
+
+    /// Format of the progress printed once the train stops.
+    #[arg(long, value_enum, default_value_t = OutputFormat::Table)]
+    pub(crate) output: OutputFormat,
+}
+
+/// Runs the release train and prints its progress.
+///
+/// Exits with 2 if the train stopped before every repo is released.
+pub(crate) fn exec(path: Option<PathBuf>, args: ReleaseTrainArgs) -> anyhow::Result<ExitCode> {
+    let manifest = load_manifest(path)?;
+    let spec = ReleaseSpec::from_file(&args.spec)?;
+    let options = ReleaseTrainOptions {
+        state_path: args.state,
+        poll_interval: Duration::from_secs(args.poll_interval),
+        once: args.once,
+        dry_run: args.dry_run,
+    };
+    let state = release_train(manifest, &spec, &options)?;
+    match args.output {
+        OutputFormat::Table => print!("{state}"),
+        OutputFormat::Json => println!("{}", serde_json::to_string_pretty(&state)?),
+    }
+    if state.is_done() || args.dry_run {
+        Ok(ExitCode::SUCCESS)
+    } else {
+        Ok(ExitCode::from(2))
+    }
+}
@@ -0,0 +1,96 @@
+use std::{path::PathBuf, process::ExitCode};
+
+use clap::Args;
+use releasy_core::event::{ClientPayload, Event, EventDetails, EventType};
+use releasy_emit::{
+    context::{resolve_commit, with_commit_metadata},
+    outbox::{emit_with_outbox, replay, OutboxLocation, DEFAULT_OUTBOX_FILE_NAME},
+    target::{RepoSelector, TargetSelection},
+    DEFAULT_CONCURRENCY,
+};
+
+use super::{
+    emit::{print_report, resolve_delivered_targets, warn_if_outbox_left_on_ci, OutputFormat},
+    load_manifest,
+};
+
+/// Flags of the `replay` command.
+#[derive(Args, Debug)]
+pub(crate) struct ReplayArgs {
+    /// Outbox holding the undelivered events, as given to `releasy emit`.
+    #[arg(long, default_value = DEFAULT_OUTBOX_FILE_NAME)]
+    pub(crate) outbox: OutboxLocation,
+
+    /// Emit an event covering the `<base>..<head>` commit range of the current repo instead of
+    /// replaying the outbox, e.g. to catch up downstream repos after an outage.
+    ///
+    /// Commits are read from the local checkout.
+    #[arg(long)]
+    pub(crate) range: Option<String>,
+
+    /// Type of the event emitted for `--range`.
+    #[arg(long, default_value = "new-commit-to-dependency", requires = "range")]
+    pub(crate) event: EventType,
+
+    /// Branch of the commits in `--range`, the default branch if omitted.
+    #[arg(long, requires = "range")]
+    pub(crate) event_branch: Option<String>,
+
+    /// Only target the matching repos with `--range`. Accepts the same forms as `releasy emit`.
+    #[arg(long, requires = "range")]
+    pub(crate) only: Vec<RepoSelector>,
+
+    /// Do not target the matching repos with `--range`.
+    #[arg(long, requires = "range")]
+    pub(crate) exclude: Vec<RepoSelector>,
+
+    /// Target every repo depending on the current repo with `--range`, directly or transitively.
+    #[arg(long, requires = "range")]
+    pub(crate) transitive: bool,
+
+    /// Maximum number of target repos an event is sent to concurrently.
+    #[arg(long, default_value_t = DEFAULT_CONCURRENCY)]
+    pub(crate) concurrency: usize,
+
+    /// Format of the report listing the outcome for each target repo.
+    #[arg(long, value_enum, default_value_t = OutputFormat::Table)]
+    pub(crate) output: OutputFormat,
+}
+
+/// Redelivers the events waiting in the outbox, or emits an event for a historical commit range.
+///
+/// Events that still cannot be delivered are kept in the outbox.
+pub(crate) async fn exec(path: Option<PathBuf>, args: ReplayArgs) -> anyhow::Result<ExitCode> {
+    let Some(range) = args.range else {
+        let report = replay(&args.outbox, args.concurrency).await?;
+        warn_if_outbox_left_on_ci(&args.outbox);
+        return print_report(&report, args.output);
+    };
+
+    let (base, head) = range
+        .split_once("..")
+        .filter(|(base, head)| !base.is_empty() && !head.is_empty() && !head.starts_with('.'))
+        .ok_or_else(|| anyhow::anyhow!("range should be in the `<base>..<head>` form"))?;
+    let resolve = |rev: &str| {
+        resolve_commit(rev)
+            .ok_or_else(|| anyhow::anyhow!("cannot find `{rev}` in the local checkout"))
+    };
+    let (base, head) = (resolve(base)?, resolve(head)?);
+    let manifest = load_manifest(path)?;
+    let details = EventDetails::new(Some(head), None)
+        .with_branch(args.event_branch)
+        .with_base_commit_hash(Some(base));
+    let event = Event::new(
+        args.event,
+        ClientPayload::new(
+            manifest.current_repo().clone(),
+            with_commit_metadata(details),
+        ),
+    );
+    let selection =
+        TargetSelection::new(args.only, args.exclude, args.transitive).with_groups(&manifest);
+    let targets = resolve_delivered_targets(&event, manifest, &selection)?;
+    let report = emit_with_outbox(&event, targets, args.concurrency, &args.outbox).await?;
+    warn_if_outbox_left_on_ci(&args.outbox);
+    print_report(&report, args.output)
+}
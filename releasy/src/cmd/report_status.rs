@@ -0,0 +1,86 @@
+use std::{env::current_dir, path::PathBuf};
+
+use clap::{Args, ValueEnum};
+use releasy_core::{
+    commit_status::{CommitState, CommitStatus},
+    repo::Repo,
+};
+use releasy_handler::commit_status::{
+    current_run_url, post_commit_status, recorded_upstream_commit,
+};
+
+use super::load_manifest;
+
+/// Flags of the `report-status` command.
+#[derive(Args, Debug)]
+pub(crate) struct ReportStatusArgs {
+    /// Result of the CI of the tracking branch.
+    #[arg(long, value_enum)]
+    pub(crate) state: StatusState,
+
+    /// Upstream repo the status is posted to, as `<owner>/<name>`.
+    ///
+    /// Defaults to the upstream recorded in the `Releasy-Upstream` trailer of `HEAD`.
+    #[arg(long, requires = "commit")]
+    pub(crate) upstream: Option<String>,
+
+    /// Upstream commit the status is posted to.
+    ///
+    /// Defaults to the commit recorded in the `Releasy-Upstream` trailer of `HEAD`.
+    #[arg(long, requires = "upstream")]
+    pub(crate) commit: Option<String>,
+
+    /// URL the status links to. Defaults to the current GitHub Actions run.
+    #[arg(long)]
+    pub(crate) target_url: Option<String>,
+
+    /// Short description shown next to the status.
+    #[arg(long)]
+    pub(crate) description: Option<String>,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum StatusState {
+    Pending,
+    Success,
+    Failure,
+    Error,
+}
+
+impl From<StatusState> for CommitState {
+    fn from(state: StatusState) -> Self {
+        match state {
+            StatusState::Pending => CommitState::Pending,
+            StatusState::Success => CommitState::Success,
+            StatusState::Failure => CommitState::Failure,
+            StatusState::Error => CommitState::Error,
+        }
+    }
+}
+
+pub(crate) async fn exec(path: Option<PathBuf>, args: ReportStatusArgs) -> anyhow::Result<()> {
+    let manifest = load_manifest(path)?;
+    let (upstream, commit_hash) = match (args.upstream, args.commit) {
+        (Some(upstream), Some(commit_hash)) => {
+            let (owner, name) = upstream
+                .split_once('/')
+                .ok_or_else(|| anyhow::anyhow!("upstream should be given as <owner>/<name>"))?;
+            (Repo::new(name.to_string(), owner.to_string()), commit_hash)
+        }
+        _ => recorded_upstream_commit("HEAD", &current_dir()?)?.ok_or_else(|| {
+            anyhow::anyhow!("HEAD does not record an upstream commit, pass --upstream and --commit")
+        })?,
+    };
+
+    let status = CommitStatus::new(args.state.into(), manifest.current_repo())
+        .with_description(args.description)
+        .with_target_url(args.target_url.or_else(current_run_url));
+    println!(
+        "Reporting {:?} as `{}` on {}/{}@{commit_hash}",
+        status.state(),
+        status.context(),
+        upstream.owner(),
+        upstream.name()
+    );
+    post_commit_status(&upstream, &commit_hash, &status).await
+}
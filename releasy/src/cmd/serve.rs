@@ -0,0 +1,56 @@
+use std::{net::SocketAddr, path::PathBuf};
+
+use clap::Args;
+use releasy_graph::source::cache_dir;
+use releasy_handler::serve::{serve, ServeConfig};
+
+use super::load_manifest;
+
+/// Environment variable holding the secret of the webhooks.
+const WEBHOOK_SECRET_ENV: &str = "RELEASY_WEBHOOK_SECRET";
+
+/// Flags of the `serve` command.
+#[derive(Args, Debug)]
+pub(crate) struct ServeArgs {
+    /// Address to listen on for webhook deliveries.
+    #[arg(long, default_value = "127.0.0.1:8080")]
+    pub(crate) addr: SocketAddr,
+
+    /// Path to the manifest of a served repo. Can be repeated to serve multiple repos, defaults to
+    /// the manifest given with `--path`.
+    #[arg(long)]
+    pub(crate) manifest: Vec<PathBuf>,
+
+    /// Directory keeping the checkouts of the served repos between events.
+    ///
+    /// Defaults to `workspaces` in the releasy cache dir.
+    #[arg(long)]
+    pub(crate) workspace_dir: Option<PathBuf>,
+
+    /// Maximum number of events waiting to be handled per repo.
+    #[arg(long, default_value_t = 16)]
+    pub(crate) queue_capacity: usize,
+}
+
+pub(crate) async fn exec(path: Option<PathBuf>, args: ServeArgs) -> anyhow::Result<()> {
+    let secret = std::env::var(WEBHOOK_SECRET_ENV)
+        .map_err(|_| anyhow::anyhow!("{WEBHOOK_SECRET_ENV} should be set"))?;
+    let manifests = if args.manifest.is_empty() {
+        vec![load_manifest(path)?]
+    } else {
+        args.manifest
+            .into_iter()
+            .map(|path| load_manifest(Some(path)))
+            .collect::<anyhow::Result<_>>()?
+    };
+    let config = ServeConfig {
+        addr: args.addr,
+        secret,
+        manifests,
+        workspace_dir: args
+            .workspace_dir
+            .unwrap_or_else(|| cache_dir().join("workspaces")),
+        queue_capacity: args.queue_capacity,
+    };
+    serve(config).await
+}
@@ -0,0 +1,40 @@
+use std::path::PathBuf;
+
+use clap::{Args, ValueEnum};
+use releasy_handler::{handle::Workspace, status::status};
+
+use super::load_manifest;
+
+/// Flags of the `status` command.
+#[derive(Args, Debug)]
+pub(crate) struct StatusArgs {
+    /// Format of the report.
+    #[arg(long, value_enum, default_value_t = StatusFormat::Table)]
+    pub(crate) output: StatusFormat,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum StatusFormat {
+    Table,
+    Json,
+    /// A Markdown table, e.g. for `$GITHUB_STEP_SUMMARY`.
+    Markdown,
+}
+
+pub(crate) fn exec(path: Option<PathBuf>, args: StatusArgs) -> anyhow::Result<()> {
+    let manifest = load_manifest(path)?;
+    let report = status(manifest, &Workspace::Temporary)?;
+    match args.output {
+        StatusFormat::Table => println!("{report}"),
+        StatusFormat::Json => println!("{}", serde_json::to_string_pretty(&report)?),
+        StatusFormat::Markdown => println!("{}", report.to_markdown()),
+    }
+    let errors = report
+        .branches()
+        .filter(|branch| branch.upstream_error().is_some())
+        .count();
+    if errors > 0 {
+        anyhow::bail!("the upstream head of {errors} tracking branches could not be read");
+    }
+    Ok(())
+}
@@ -0,0 +1,71 @@
+use std::{collections::BTreeSet, path::PathBuf, str::FromStr};
+
+use clap::Args;
+use releasy_core::event::EventType;
+use releasy_graph::{manifest::ManifestFile, plan::Plan};
+
+use super::manifest_path;
+
+/// Flags of the `validate` command.
+#[derive(Args, Debug)]
+pub(crate) struct ValidateArgs {
+    /// Fail if parsing the manifest produces any warnings.
+    #[arg(long)]
+    pub(crate) deny_warnings: bool,
+}
+
+/// Checks that the manifest can be parsed, that a plan can be built from it and that the current
+/// repo is part of the plan.
+pub(crate) fn exec(path: Option<PathBuf>, args: ValidateArgs) -> anyhow::Result<()> {
+    let manifest_file = ManifestFile::from_file(&manifest_path(path)?)?;
+    let warnings = manifest_file.warnings().count();
+    for warning in manifest_file.warnings() {
+        println!("WARNING: {warning}");
+    }
+    if args.deny_warnings && warnings > 0 {
+        anyhow::bail!("manifest produced {warnings} warnings");
+    }
+
+    let manifest = manifest_file.manifest();
+    let current_repo = manifest.current_repo().clone();
+    let mut declared_events = vec![];
+    for (name, _) in manifest.event_actions() {
+        let event_type = EventType::from_str(name)?;
+        if !event_type.is_custom() {
+            anyhow::bail!("`{name}` is a builtin event and cannot be declared in `[events]`");
+        }
+        declared_events.push(event_type);
+    }
+    let plan = Plan::try_from_manifest(manifest)?;
+
+    // Edges can subscribe to events that only other repos declare, but a typo in a builtin event
+    // name also ends up as an undeclared custom event.
+    let undeclared_events = plan
+        .graph()
+        .edge_weights()
+        .flat_map(|edge| edge.events())
+        .filter(|event_type| event_type.is_custom() && !declared_events.contains(event_type))
+        .map(|event_type| event_type.to_string())
+        .collect::<BTreeSet<_>>();
+    for event_type in &undeclared_events {
+        println!("WARNING: `{event_type}` events are not declared in `[events]`");
+    }
+    if args.deny_warnings && !undeclared_events.is_empty() {
+        anyhow::bail!(
+            "manifest subscribes to {} undeclared events",
+            undeclared_events.len()
+        );
+    }
+    let upstream_repos = plan.upstream_repos(current_repo.clone())?.count();
+    let downstream_repos = plan.downstream_repos(current_repo.clone())?.count();
+
+    println!(
+        "Manifest is valid: {} repos, {} dependencies",
+        plan.graph().node_count(),
+        plan.graph().edge_count()
+    );
+    println!(
+        "Current repo ({current_repo}) has {upstream_repos} upstream and {downstream_repos} downstream repos"
+    );
+    Ok(())
+}
@@ -0,0 +1,26 @@
+mod cmd;
+
+use std::process::ExitCode;
+
+use clap::Parser;
+use cmd::{Cli, Command};
+
+#[tokio::main]
+async fn main() -> anyhow::Result<ExitCode> {
+    let cli = Cli::parse();
+    match cli.command {
+        Command::Emit(args) => return cmd::emit::exec(cli.path, args).await,
+        Command::Replay(args) => return cmd::replay::exec(cli.path, args).await,
+        Command::Handle(args) => cmd::handle::exec(cli.path, args),
+        Command::Serve(args) => cmd::serve::exec(cli.path, args).await,
+        Command::Graph(args) => cmd::graph::exec(cli.path, args),
+        Command::Validate(args) => cmd::validate::exec(cli.path, args),
+        Command::Reconcile(args) => return cmd::reconcile::exec(cli.path, args),
+        Command::Status(args) => cmd::status::exec(cli.path, args),
+        Command::ReportStatus(args) => cmd::report_status::exec(cli.path, args).await,
+        Command::ReleaseTrain(args) => return cmd::release_train::exec(cli.path, args),
+        Command::Changelog(args) => cmd::changelog::exec(cli.path, args),
+        Command::Doctor => cmd::doctor::exec(cli.path),
+    }?;
+    Ok(ExitCode::SUCCESS)
+}